@@ -0,0 +1,49 @@
+//! Times move generation and board lookups on the states of the example
+//! game. Run with `cargo bench`.
+
+use std::{fs, hint::black_box, path::PathBuf, str::FromStr, time::{Duration, Instant}};
+
+use socha_client_2024::{game::{perft, State}, util::Element};
+
+/// Runs the function repeatedly for at least the given duration and returns
+/// the average time per run.
+fn measure(duration: Duration, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || start.elapsed() < duration {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let dir = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap().join("test-resources").join("example-game");
+    let mut paths: Vec<_> = fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.to_string_lossy().ends_with(".state.xml"))
+        .collect();
+    paths.sort();
+    let states: Vec<State> = paths.iter()
+        .map(|p| State::try_from(&Element::from_str(&fs::read_to_string(p).unwrap()).unwrap()).unwrap())
+        .collect();
+
+    let moves = measure(Duration::from_secs(3), || {
+        for state in &states {
+            black_box(perft(black_box(state), 1));
+        }
+    });
+    println!("perft 1 over {} states: {:?} per state", states.len(), moves / states.len() as u32);
+
+    let lookups = measure(Duration::from_secs(1), || {
+        for state in &states {
+            let board = state.board();
+            for (position, _) in board.fields() {
+                black_box(board.get(black_box(position)));
+                black_box(board.does_field_have_current(black_box(position)));
+            }
+        }
+    });
+    let fields: usize = states.iter().map(|s| s.board().fields().count()).sum();
+    println!("get and does_field_have_current: {:?} per field", lookups / fields as u32);
+}
@@ -0,0 +1,187 @@
+use std::{str::FromStr, time::Duration};
+
+use clap::Parser;
+use log::{info, LevelFilter};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use simplelog::{Config, SimpleLogger};
+use socha_client_2024::client::{GameClientDelegate, MOVE_TIME, NETWORK_MARGIN};
+use socha_client_2024::game::{Move, Rules, State, Team};
+use socha_client_2024::search::{AlphaBeta, Evaluator, FeatureEvaluator, Mcts, MoveGeneration};
+use socha_client_2024::tournament::{Pairing, Sprt, Tournament};
+use socha_client_2024::util::{Error, Perform, Result};
+
+/// Plays strategies against each other on generated boards.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The strategies to play, each given as `<name>[:<weights>]`, e.g.
+    /// `mcts` or `alphabeta:passengers=80,coal=2`. Every pair of strategies
+    /// plays, and the later one is tested for being stronger.
+    #[clap(required_unless_present = "list")]
+    strategies: Vec<String>,
+    /// Lists the registered strategies.
+    #[clap(long)]
+    list: bool,
+    /// The number of boards to play on. Each board is played twice, once
+    /// with each strategy starting.
+    #[clap(short = 'n', long, default_value_t = 10)]
+    boards: u64,
+    /// The seed of the first board.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// Places sandbanks on the generated boards.
+    #[clap(long)]
+    sandbanks: bool,
+    /// The time limit per move in milliseconds.
+    #[clap(long, default_value_t = MOVE_TIME.as_millis() as u64)]
+    move_time: u64,
+    /// The time in milliseconds the strategies leave unused, as if sending
+    /// the move to the server.
+    #[clap(long, default_value_t = NETWORK_MARGIN.as_millis() as u64)]
+    network_margin: u64,
+    /// The Elo difference the null hypothesis of the SPRT assumes.
+    #[clap(long, default_value_t = 0.0)]
+    elo0: f64,
+    /// The Elo difference the alternative hypothesis of the SPRT assumes.
+    #[clap(long, default_value_t = 10.0)]
+    elo1: f64,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Warn")]
+    level: String,
+}
+
+/// A strategy that can be played in a tournament.
+struct Strategy {
+    name: &'static str,
+    description: &'static str,
+    create: fn(FeatureEvaluator, Duration) -> Box<dyn GameClientDelegate>,
+}
+
+/// The registered strategies.
+const STRATEGIES: [Strategy; 4] = [
+    Strategy {
+        name: "random",
+        description: "Picks a random sensible move.",
+        create: |_, _| Box::new(RandomMoves(StdRng::from_entropy())),
+    },
+    Strategy {
+        name: "greedy",
+        description: "Picks the sensible move with the best evaluation.",
+        create: |evaluator, _| Box::new(Greedy(evaluator)),
+    },
+    Strategy {
+        name: "alphabeta",
+        description: "Searches sensible moves with alpha-beta pruning.",
+        create: |evaluator, budget| Box::new(AlphaBeta::new(evaluator).moves(MoveGeneration::Sensible).budget(budget)),
+    },
+    Strategy {
+        name: "mcts",
+        description: "Runs a Monte Carlo tree search.",
+        create: |evaluator, budget| Box::new(Mcts::new(evaluator).budget(budget)),
+    },
+];
+
+/// Picks random sensible moves.
+struct RandomMoves(StdRng);
+
+impl GameClientDelegate for RandomMoves {
+    fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+        state.sensible_moves().choose(&mut self.0).expect("No move found!").clone()
+    }
+}
+
+/// Picks the sensible move with the best evaluation.
+struct Greedy(FeatureEvaluator);
+
+impl GameClientDelegate for Greedy {
+    fn pick_move(&mut self, state: &State, my_team: Team) -> Move {
+        state.sensible_moves().into_iter()
+            .max_by_key(|m| state.child(m.clone()).map(|s| self.0.evaluate(&s, my_team)).unwrap_or(i32::MIN))
+            .expect("No move found!")
+    }
+}
+
+/// Parses a strategy given as `<name>[:<weights>]`.
+fn parse_strategy(spec: &str) -> Result<(&'static Strategy, FeatureEvaluator)> {
+    let (name, weights) = spec.split_once(':').unwrap_or((spec, ""));
+    let strategy = STRATEGIES.iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| Error::UnknownVariant(format!("Unknown strategy {}, see --list", name)))?;
+    Ok((strategy, FeatureEvaluator::from_str(weights)?))
+}
+
+/// Prints the results of a pair of strategies.
+fn print_pairing(tournament: &Tournament, pairing: &Pairing, sprt: Sprt) {
+    let names = pairing.entrants.map(|e| tournament.entrants()[e].name.as_str());
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0).max(8);
+    println!();
+    println!("{} vs {} ({} games)", names[0], names[1], pairing.records[0].games());
+    println!("{:<width$} {:>4} {:>4} {:>4} {:>7} {:>7}", "", "W", "D", "L", "Score", "Points");
+    for (name, record) in names.iter().zip(&pairing.records) {
+        println!("{:<width$} {:>4} {:>4} {:>4} {:>6.1}% {:>7.1}", name, record.wins, record.draws, record.losses, 100.0 * record.score(), record.average_points());
+    }
+    for (name, record) in names.iter().zip(&pairing.records) {
+        for (kind, count) in &record.violations {
+            println!("{} lost {} game(s) by {}", name, count, kind);
+        }
+        if record.timeouts > 0 {
+            println!("{} lost {} game(s) by timeout", name, record.timeouts);
+        }
+    }
+
+    let record = &pairing.records[1];
+    if let Some(elo) = record.elo() {
+        println!("Elo of {} relative to {}: {} (95%)", names[1], names[0], elo);
+    }
+    let (lower, upper) = sprt.bounds();
+    println!("SPRT ({}): LLR {:.2} in [{:.2}, {:.2}], {} is {}", sprt, sprt.llr(record), lower, upper, names[1], sprt.verdict(record));
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    if args.list {
+        for strategy in &STRATEGIES {
+            println!("{:<10} {}", strategy.name, strategy.description);
+        }
+        return Ok(());
+    }
+    if args.strategies.len() < 2 {
+        return Err(Error::Custom("At least two strategies are needed".to_owned()));
+    }
+
+    let move_time = Duration::from_millis(args.move_time);
+    let budget = move_time.saturating_sub(Duration::from_millis(args.network_margin));
+    let rules = if args.sandbanks { Rules::with_sandbanks() } else { Rules::default() };
+    let mut tournament = Tournament::new()
+        .boards(args.boards)
+        .seed(args.seed)
+        .rules(rules)
+        .move_time(Some(move_time));
+    for spec in &args.strategies {
+        let (strategy, evaluator) = parse_strategy(spec)?;
+        let create = strategy.create;
+        tournament = tournament.entrant(spec.as_str(), move || create(evaluator, budget));
+    }
+
+    let pairs = args.strategies.len() * (args.strategies.len() - 1) / 2;
+    let total = 2 * args.boards as usize * pairs;
+    let mut played = 0;
+    let pairings = tournament.run(|report| {
+        played += 1;
+        let names = report.entrants.map(|e| tournament.entrants()[e].name.as_str());
+        let outcome = match report.result.winner() {
+            Some(winner) => format!("{} wins", names[winner.team().index()]),
+            None => "draw".to_owned(),
+        };
+        let forfeit = report.forfeit.map(|(team, forfeit)| format!(" ({} forfeited: {:?})", names[team.index()], forfeit)).unwrap_or_default();
+        println!("Game {}/{} on board {}: {} vs {}, {}{}", played, total, report.seed, names[0], names[1], outcome, forfeit);
+        info!("Result: {:?}", report.result);
+    });
+
+    let sprt = Sprt::new(args.elo0, args.elo1);
+    for pairing in &pairings {
+        print_pairing(&tournament, pairing, sprt);
+    }
+    Ok(())
+}
@@ -1,5 +1,9 @@
 use std::net::TcpStream;
-use std::io::{self, BufWriter, BufReader, Read, Write};
+use std::io::{self, BufRead, BufWriter, BufReader, Read, Write};
+use std::sync::{mpsc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use log::{info, warn, debug, error};
 use quick_xml::events::{Event as XmlEvent, BytesStart};
 use quick_xml::{Reader, Writer};
@@ -24,6 +28,115 @@ pub trait GameClientDelegate {
     /// Requests a move from the delegate. This method
     /// should implement the "main" game logic.
     fn pick_move(&mut self, state: &State, my_team: Team) -> Move;
+
+    /// Requests a move that should be picked before the
+    /// deadline. If the delegate takes longer, the client
+    /// sends the last move reported to `best` instead.
+    /// The client still waits for this method to return
+    /// before reading the next message, so delegates
+    /// should stop soon after the deadline.
+    /// Defaults to `pick_move`.
+    fn pick_move_until(&mut self, state: &State, my_team: Team, _deadline: Deadline, _best: &BestMove) -> Move {
+        self.pick_move(state, my_team)
+    }
+
+    /// Invoked while the opponent is to move, if pondering
+    /// is enabled, with the next message being read on a
+    /// background thread. The delegate may search ahead
+    /// until `stop` is set, which happens as soon as the
+    /// message arrives, and reuse the work once the
+    /// opponent's move is known.
+    fn ponder(&mut self, _state: &State, _my_team: Team, _stop: &AtomicBool) {}
+}
+
+impl<D> GameClientDelegate for Box<D> where D: GameClientDelegate + ?Sized {
+    fn state_updated(&mut self, state: &State) {
+        (**self).state_updated(state)
+    }
+
+    fn game_ended(&mut self, result: &GameResult) {
+        (**self).game_ended(result)
+    }
+
+    fn welcome_received(&mut self, team: Team) {
+        (**self).welcome_received(team)
+    }
+
+    fn pick_move(&mut self, state: &State, my_team: Team) -> Move {
+        (**self).pick_move(state, my_team)
+    }
+
+    fn pick_move_until(&mut self, state: &State, my_team: Team, deadline: Deadline, best: &BestMove) -> Move {
+        (**self).pick_move_until(state, my_team, deadline, best)
+    }
+
+    fn ponder(&mut self, state: &State, my_team: Team, stop: &AtomicBool) {
+        (**self).ponder(state, my_team, stop)
+    }
+}
+
+/// A point in time by which a move has to be picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Deadline {
+    start: Instant,
+    end: Instant,
+}
+
+impl Deadline {
+    /// Creates a deadline the given time after the start.
+    pub fn new(start: Instant, budget: Duration) -> Self {
+        Self { start, end: start + budget }
+    }
+
+    /// Creates a deadline the given time from now.
+    pub fn after(budget: Duration) -> Self {
+        Self::new(Instant::now(), budget)
+    }
+
+    /// The point in time the deadline is at.
+    pub fn instant(self) -> Instant {
+        self.end
+    }
+
+    /// The time since the start.
+    pub fn elapsed(self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The time left until the deadline.
+    pub fn remaining(self) -> Duration {
+        self.end.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(self) -> bool {
+        Instant::now() >= self.end
+    }
+
+    /// A deadline after the given fraction of the time
+    /// between the start and this deadline.
+    pub fn fraction(self, fraction: f64) -> Self {
+        Self::new(self.start, (self.end - self.start).mul_f64(fraction.clamp(0.0, 1.0)))
+    }
+}
+
+/// The best move a delegate found so far, which is sent
+/// if it doesn't answer in time.
+#[derive(Debug, Default)]
+pub struct BestMove {
+    m: Mutex<Option<Move>>,
+}
+
+impl BestMove {
+    /// Reports a new best move.
+    pub fn report(&self, m: Move) {
+        *self.m.lock().unwrap() = Some(m);
+    }
+
+    /// Takes the last reported move.
+    pub fn take(&self) -> Option<Move> {
+        self.m.lock().unwrap().take()
+    }
 }
 
 /// A configuration that determines whether
@@ -40,15 +153,43 @@ pub struct GameClient<D> where D: GameClientDelegate {
     delegate: D,
     debug_mode: DebugMode,
     reservation_code: Option<String>,
+    move_time: Duration,
+    network_margin: Duration,
+    ponder: bool,
     // TODO: Add game state
 }
 
+/// The time the server gives for a move.
+pub const MOVE_TIME: Duration = Duration::from_secs(2);
+/// The time reserved for sending a move to the server by default.
+pub const NETWORK_MARGIN: Duration = Duration::from_millis(300);
+
 impl<D> GameClient<D> where D: GameClientDelegate {
     /// Creates a new client using the specified delegate.
     pub fn new(delegate: D, debug_mode: DebugMode, reservation_code: Option<String>) -> Self {
-        Self { delegate, debug_mode, reservation_code }
+        Self { delegate, debug_mode, reservation_code, move_time: MOVE_TIME, network_margin: NETWORK_MARGIN, ponder: false }
+    }
+
+    /// Sets the time the server gives for a move.
+    pub fn move_time(mut self, move_time: Duration) -> Self {
+        self.move_time = move_time;
+        self
+    }
+
+    /// Sets the time reserved for sending a move, which
+    /// is subtracted from the move time.
+    pub fn network_margin(mut self, network_margin: Duration) -> Self {
+        self.network_margin = network_margin;
+        self
     }
     
+    /// Sets whether the delegate ponders while the
+    /// opponent is to move.
+    pub fn ponder(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
     /// Blocks the thread and begins reading XML messages
     /// from the provided address via TCP.
     pub fn connect(self, host: &str, port: u16) -> Result<GameResult> {
@@ -77,7 +218,7 @@ impl<D> GameClient<D> where D: GameClientDelegate {
     
     /// Blocks the thread and parses/handles game messages
     /// from the provided reader.
-    fn run(mut self, read: impl Read, write: impl Write) -> Result<GameResult> {
+    fn run(mut self, read: impl Read + Send, write: impl Write + Send) -> Result<GameResult> {
         let mut buf = Vec::new();
         let mut reader = Reader::from_reader(BufReader::new(read));
         let mut writer = Writer::new(BufWriter::new(write));
@@ -108,9 +249,14 @@ impl<D> GameClient<D> where D: GameClientDelegate {
 
         // Handle events from the server
         let mut state: Option<State> = None;
+        let mut my_team: Option<Team> = None;
         let mut game_result: Option<GameResult> = None;
         loop {
-            let event_xml = Element::read_from(&mut reader)?;
+            let ponder_state = state.as_ref()
+                .zip(my_team)
+                .filter(|(s, team)| self.ponder && s.current_team() != *team && !s.is_over());
+            let event_xml = Self::read_event(&mut self.delegate, &mut reader, ponder_state)?;
+            let received = Instant::now();
 
             debug!("Got event {}", event_xml);
             match Event::try_from(&event_xml) {
@@ -124,7 +270,10 @@ impl<D> GameClient<D> where D: GameClientDelegate {
                 Ok(Event::Room { room_id, payload }) => {
                     info!("Got {} in room {}", payload, room_id);
                     match payload {
-                        EventPayload::Welcome(team) => self.delegate.welcome_received(team),
+                        EventPayload::Welcome(team) => {
+                            my_team = Some(team);
+                            self.delegate.welcome_received(team);
+                        },
                         EventPayload::GameResult(result) => {
                             self.delegate.game_ended(&result);
                             game_result = Some(result);
@@ -136,10 +285,17 @@ impl<D> GameClient<D> where D: GameClientDelegate {
                         EventPayload::MoveRequest => {
                             let state = state.as_ref().ok_or_else(|| Error::InvalidState("No state available at move request!".to_owned()))?;
                             let team = state.current_team();
-                            let new_move = self.delegate.pick_move(state, team);
-                            let request = Request::Room { room_id, payload: RequestPayload::Move(new_move) };
-                            let request_xml = Element::from(request);
-                            request_xml.write_to(&mut writer)?;
+                            let deadline = Deadline::new(received, self.move_time.saturating_sub(self.network_margin));
+                            Self::pick_move_in_time(&mut self.delegate, state, team, deadline, |new_move| {
+                                if cfg!(debug_assertions) {
+                                    if let Err(report) = state.validate(&new_move) {
+                                        warn!("Sending invalid move {:?}: {}", new_move, report);
+                                    }
+                                }
+                                let request = Request::Room { room_id, payload: RequestPayload::Move(new_move) };
+                                let request_xml = Element::from(request);
+                                request_xml.write_to(&mut writer)
+                            })?;
                         },
                     };
                 },
@@ -161,4 +317,239 @@ impl<D> GameClient<D> where D: GameClientDelegate {
             Err(Error::InvalidState("Failed to receive game_result".to_string()))
         }
     }
+
+    /// Reads the next event, letting the delegate ponder on the given state
+    /// in the meantime if there is one. The event is read on a separate
+    /// thread, so that the delegate stays on this one.
+    fn read_event(delegate: &mut D, reader: &mut Reader<impl BufRead + Send>, ponder: Option<(&State, Team)>) -> Result<Element> {
+        let Some((state, team)) = ponder else {
+            return Element::read_from(reader);
+        };
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let stop = &stop;
+            let event = scope.spawn(move || {
+                let event = Element::read_from(reader);
+                stop.store(true, Ordering::Relaxed);
+                event
+            });
+            delegate.ponder(state, team, stop);
+            event.join().expect("Reader panicked")
+        })
+    }
+
+    /// Asks the delegate for a move and sends it, watching the deadline on a
+    /// separate thread. If the delegate doesn't answer by the deadline, the
+    /// best move it reported is sent right away, falling back to a simple
+    /// move. A late answer is awaited, but discarded.
+    fn pick_move_in_time(delegate: &mut D, state: &State, team: Team, deadline: Deadline, send: impl FnOnce(Move) -> Result<()> + Send) -> Result<()> {
+        let best = BestMove::default();
+        let send = Mutex::new(Some(send));
+        // Only the first move is sent, whichever thread gets there first
+        let send_once = |m: Move| match send.lock().unwrap().take() {
+            Some(send) => send(m),
+            None => Ok(()),
+        };
+        thread::scope(|scope| {
+            let (answered, answer) = mpsc::channel::<()>();
+            let best = &best;
+            let send_once = &send_once;
+            let watchdog = scope.spawn(move || match answer.recv_timeout(deadline.remaining()) {
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let m = best.take().or_else(|| Self::fallback_move(state)).expect("No move found!");
+                    warn!("Delegate missed the deadline after {:?}, sending {}", deadline.elapsed(), m);
+                    send_once(m)
+                },
+                _ => Ok(()),
+            });
+            let sent = send_once(delegate.pick_move_until(state, team, deadline, best));
+            drop(answered);
+            watchdog.join().expect("Watchdog panicked").and(sent)
+        })
+    }
+
+    /// A move that is quick to find.
+    fn fallback_move(state: &State) -> Option<Move> {
+        state.simple_moves().into_iter().next().or_else(|| state.possible_moves().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io::{self, BufReader, Read}, rc::Rc, sync::{atomic::{AtomicBool, Ordering}, mpsc}, thread, time::{Duration, Instant}};
+
+    use quick_xml::Reader;
+
+    use crate::game::{Board, Move, State, Team};
+    use crate::search::{AlphaBeta, FeatureEvaluator, MoveGeneration};
+
+    use super::{BestMove, Deadline, GameClient, GameClientDelegate};
+
+    /// A delegate that answers right away, optionally reporting a move first.
+    struct Reporting {
+        report: Option<Move>,
+    }
+
+    impl GameClientDelegate for Reporting {
+        fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+            state.simple_moves().pop().unwrap()
+        }
+
+        fn pick_move_until(&mut self, state: &State, my_team: Team, _deadline: Deadline, best: &BestMove) -> Move {
+            if let Some(m) = self.report.clone() {
+                best.report(m);
+            }
+            self.pick_move(state, my_team)
+        }
+    }
+
+    /// Wraps a delegate so that it only answers once a move was sent, giving
+    /// up after a long time, and records whether it was.
+    struct Late<D> {
+        delegate: D,
+        sent: mpsc::Receiver<()>,
+        answered_after_send: bool,
+    }
+
+    impl<D> Late<D> {
+        /// Wraps the delegate, returning the sender to notify once a move
+        /// was sent.
+        fn new(delegate: D) -> (Self, mpsc::Sender<()>) {
+            let (sender, sent) = mpsc::channel();
+            (Self { delegate, sent, answered_after_send: false }, sender)
+        }
+    }
+
+    impl<D> GameClientDelegate for Late<D> where D: GameClientDelegate {
+        fn pick_move(&mut self, state: &State, my_team: Team) -> Move {
+            self.delegate.pick_move(state, my_team)
+        }
+
+        fn pick_move_until(&mut self, state: &State, my_team: Team, deadline: Deadline, best: &BestMove) -> Move {
+            let m = self.delegate.pick_move_until(state, my_team, deadline, best);
+            self.answered_after_send = self.sent.recv_timeout(Duration::from_secs(30)).is_ok();
+            m
+        }
+    }
+
+    /// Requests a move from the delegate and returns the sent move, notifying
+    /// the given sender once it was sent.
+    fn request<D>(delegate: &mut D, state: &State, budget: Duration, notify: mpsc::Sender<()>) -> Move where D: GameClientDelegate {
+        let mut sent = None;
+        GameClient::pick_move_in_time(delegate, state, state.current_team(), Deadline::after(budget), |m| {
+            sent = Some(m);
+            let _ = notify.send(());
+            Ok(())
+        }).unwrap();
+        sent.unwrap()
+    }
+
+    #[test]
+    fn test_answer_in_time() {
+        let state = State::new(Board::generate(0));
+        let mut delegate = Reporting { report: None };
+        let m = request(&mut delegate, &state, Duration::from_secs(5), mpsc::channel().0);
+        assert_eq!(m, state.simple_moves().pop().unwrap());
+    }
+
+    #[test]
+    fn test_late_answer_sends_best_move() {
+        let state = State::new(Board::generate(0));
+        let reported = state.simple_moves()[1].clone();
+        let (mut delegate, notify) = Late::new(Reporting { report: Some(reported.clone()) });
+        let m = request(&mut delegate, &state, Duration::from_millis(50), notify);
+        assert_eq!(m, reported);
+        assert!(delegate.answered_after_send, "The move should be sent before the delegate answers");
+    }
+
+    #[test]
+    fn test_late_answer_falls_back() {
+        let state = State::new(Board::generate(0));
+        let (mut delegate, notify) = Late::new(Reporting { report: None });
+        let m = request(&mut delegate, &state, Duration::from_millis(20), notify);
+        assert_eq!(m, state.simple_moves()[0]);
+        assert!(delegate.answered_after_send, "The fallback should be sent before the delegate answers");
+    }
+
+    #[test]
+    fn test_late_engine_sends_reported_move() {
+        let state = State::new(Board::generate(0));
+        let engine = || AlphaBeta::new(FeatureEvaluator::new()).max_depth(1).moves(MoveGeneration::Sensible);
+        let expected = engine().search(&state, Duration::from_secs(5)).unwrap().best;
+        assert_ne!(expected, state.simple_moves()[0], "The engine's move should differ from the fallback");
+        // The engine finishes its single depth well before the deadline, but
+        // only answers once a move was sent
+        let (mut delegate, notify) = Late::new(engine());
+        let m = request(&mut delegate, &state, Duration::from_secs(1), notify);
+        assert_eq!(m, expected);
+        assert!(delegate.answered_after_send, "The reported move should be sent before the engine answers");
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Instant::now();
+        let deadline = Deadline::new(start, Duration::from_millis(100));
+        assert_eq!(deadline.instant(), start + Duration::from_millis(100));
+        assert_eq!(deadline.fraction(0.5).instant(), start + Duration::from_millis(50));
+        assert!(deadline.remaining() <= Duration::from_millis(100));
+        assert!(Deadline::after(Duration::ZERO).is_expired());
+    }
+
+    /// A delegate that counts how long it pondered. The shared counter
+    /// keeps it from being `Send`.
+    #[derive(Default)]
+    struct Ponderer {
+        pondered: Rc<Cell<u32>>,
+    }
+
+    impl GameClientDelegate for Ponderer {
+        fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+            state.simple_moves().pop().unwrap()
+        }
+
+        fn ponder(&mut self, _state: &State, _my_team: Team, stop: &AtomicBool) {
+            while !stop.load(Ordering::Relaxed) {
+                self.pondered.set(self.pondered.get() + 1);
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// A stream that only becomes readable after a delay.
+    struct Delayed {
+        delay: Duration,
+        data: &'static [u8],
+    }
+
+    impl Read for Delayed {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(std::mem::take(&mut self.delay));
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_ponders_while_reading() {
+        let state = State::new(Board::generate(0));
+        let team = state.current_team().opponent();
+        let mut delegate = Ponderer::default();
+        let mut reader = Reader::from_reader(BufReader::new(Delayed { delay: Duration::from_millis(50), data: b"<left roomId=\"1\"/>" }));
+        let element = GameClient::read_event(&mut delegate, &mut reader, Some((&state, team))).unwrap();
+        assert_eq!(element.name(), "left");
+        assert!(delegate.pondered.get() > 0);
+
+        // Without a state to ponder on, the delegate is left alone
+        let pondered = delegate.pondered.get();
+        let mut reader = Reader::from_reader(BufReader::new(Delayed { delay: Duration::from_millis(10), data: b"<left/>" }));
+        GameClient::read_event(&mut delegate, &mut reader, None).unwrap();
+        assert_eq!(delegate.pondered.get(), pondered);
+    }
+
+    #[test]
+    fn test_delegate_stays_on_client_thread() {
+        let state = State::new(Board::generate(0));
+        let mut delegate = Ponderer::default();
+        let m = request(&mut delegate, &state, Duration::from_secs(5), mpsc::channel().0);
+        assert_eq!(m, state.simple_moves().pop().unwrap());
+    }
 }
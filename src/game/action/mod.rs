@@ -10,6 +10,8 @@ pub use advance::*;
 pub use push::*;
 pub use turn::*;
 
+use std::fmt;
+
 use crate::util::{Error, Element, Result};
 
 use super::CubeDir;
@@ -49,6 +51,17 @@ impl Action {
     }
 }
 
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accelerate(Accelerate { acc }) => write!(f, "Accelerate {:+}", acc),
+            Self::Advance(Advance { distance }) => write!(f, "Advance {}", distance),
+            Self::Push(Push { direction }) => write!(f, "Push {}", direction),
+            Self::Turn(Turn { direction }) => write!(f, "Turn {}", direction),
+        }
+    }
+}
+
 impl TryFrom<&Element> for Action {
     type Error = Error;
 
@@ -80,6 +93,15 @@ mod tests {
 
     // TODO: Add parse and/or roundtrip tests
 
+    #[test]
+    fn test_display() {
+        assert_eq!(Action::accelerate(2).to_string(), "Accelerate +2");
+        assert_eq!(Action::accelerate(-1).to_string(), "Accelerate -1");
+        assert_eq!(Action::advance(-1).to_string(), "Advance -1");
+        assert_eq!(Action::push(CubeDir::Left).to_string(), "Push LEFT");
+        assert_eq!(Action::turn(CubeDir::UpRight).to_string(), "Turn UP_RIGHT");
+    }
+
     #[test]
     fn test_xml_formats() {
         assert_xml_format!(
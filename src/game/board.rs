@@ -1,18 +1,172 @@
 //! Ported from https://github.com/software-challenge/backend/blob/be88340f619892fe70c4cbd45e131d5445e883c7/plugin/src/main/kotlin/sc/plugin2024/Board.kt
 
-use std::ops::{Range, Index, IndexMut};
+use std::{ops::{Range, Index, IndexMut}, sync::{Arc, OnceLock}};
+
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
 
 use crate::util::{Element, Error, Result, Vec2};
 
-use super::{CubeDir, Segment, CubeVec, Field, Ship, POINTS_PER_SEGMENT, POINTS_PER_PASSENGER};
+use super::{CubeDir, DistanceMap, Distances, FieldIndex, Layout, Segment, CubeVec, Field, Ship, POINTS_PER_SEGMENT, POINTS_PER_PASSENGER, NUMBER_OF_SEGMENTS, NUMBER_OF_PASSENGERS, SEGMENT_FIELDS_WIDTH, SEGMENT_FIELDS_HEIGHT, MIN_ISLANDS, MAX_ISLANDS, Rules};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Eq)]
 pub struct Board {
-    pub segments: Vec<Segment>,
-    pub next_direction: CubeDir,
+    segments: Vec<Segment>,
+    next_direction: CubeDir,
+    /// The index for looking up fields by position, shared between clones.
+    index: Arc<FieldIndex>,
+    /// The cached distances to the goal and the passengers, computed on
+    /// first use and shared between clones.
+    distances: Arc<OnceLock<DistanceMap>>,
 }
 
 impl Board {
+    /// Creates a board from the given segments.
+    pub fn new(segments: Vec<Segment>, next_direction: CubeDir) -> Self {
+        let mut board = Self { segments, next_direction, index: Arc::default(), distances: Arc::default() };
+        board.reindex();
+        board
+    }
+
+    /// Generates a new board with all segments revealed. The same seed
+    /// always yields the same board.
+    pub fn generate(seed: u64) -> Self {
+        Self::generate_with_rules(seed, Rules::default())
+    }
+
+    /// Generates a new board under the given rule variant. The same seed and
+    /// rules always yield the same board, using a random number generator
+    /// whose output doesn't change between versions of `rand`.
+    pub fn generate_with_rules(seed: u64, rules: Rules) -> Self {
+        Self::generate_with(rules, &mut ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Generates a new board under the given rule variant using the given
+    /// source of randomness.
+    pub fn generate_with(rules: Rules, rng: &mut impl Rng) -> Self {
+        let mut passenger_segments: Vec<usize> = (1..(NUMBER_OF_SEGMENTS - 1)).collect();
+        passenger_segments.shuffle(rng);
+        passenger_segments.truncate(NUMBER_OF_PASSENGERS);
+
+        let mut segments: Vec<Segment> = Vec::with_capacity(NUMBER_OF_SEGMENTS);
+        for i in 0..NUMBER_OF_SEGMENTS {
+            let (direction, center) = match segments.last() {
+                Some(previous) => Self::pick_next_placement(&segments, previous.direction, rng),
+                None => (CubeDir::Right, CubeVec::ZERO),
+            };
+            let mut segment = Segment {
+                direction,
+                center,
+                fields: vec![vec![Field::Water; SEGMENT_FIELDS_HEIGHT]; SEGMENT_FIELDS_WIDTH],
+            };
+            if i > 0 {
+                segment.populate(
+                    i == NUMBER_OF_SEGMENTS - 1,
+                    passenger_segments.contains(&i),
+                    rng.gen_range(MIN_ISLANDS..=MAX_ISLANDS),
+                    rng.gen_range(rules.sandbank_range()),
+                    rng,
+                );
+            }
+            segments.push(segment);
+        }
+
+        let last = segments.last().unwrap();
+        let next_direction = Self::pick_next_placement(&segments, last.direction, rng).0;
+        Self::new(segments, next_direction)
+    }
+
+    /// Picks the direction and center of the segment following the given
+    /// segments. The direction deviates by at most one turn from the previous
+    /// one, never points left and avoids overlapping earlier segments.
+    fn pick_next_placement(segments: &[Segment], previous_dir: CubeDir, rng: &mut impl Rng) -> (CubeDir, CubeVec) {
+        let previous_center = segments.last().map(|s| s.center).unwrap_or(CubeVec::ZERO);
+        let placement = |direction: CubeDir| (direction, previous_center + CubeVec::from(direction) * SEGMENT_FIELDS_WIDTH as i32);
+        let candidates: Vec<_> = [-1, 0, 1].into_iter()
+            .map(|turns| placement(previous_dir.rotated_by(turns)))
+            .filter(|&(direction, center)| {
+                let candidate = Segment {
+                    direction,
+                    center,
+                    fields: vec![vec![Field::Water; SEGMENT_FIELDS_HEIGHT]; SEGMENT_FIELDS_WIDTH],
+                };
+                CubeDir::Right.turn_count_to(direction).abs() < 3
+                && candidate.global_positions().all(|p| segments.iter().all(|s| s.get_global(p).is_none()))
+            })
+            .collect();
+        candidates.choose(rng).copied().unwrap_or_else(|| placement(previous_dir))
+    }
+
+    /// The revealed segments.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The direction the segment after the revealed ones lies in.
+    pub fn next_direction(&self) -> CubeDir {
+        self.next_direction
+    }
+
+    /// Edits the segments with the given function, rebuilding the field
+    /// index afterwards.
+    pub fn edit_segments<T>(&mut self, edit: impl FnOnce(&mut Vec<Segment>) -> T) -> T {
+        let result = edit(&mut self.segments);
+        self.reindex();
+        result
+    }
+
+    /// Reveals the given segment, followed by a segment in the given direction.
+    pub fn add_segment(&mut self, segment: Segment, next_direction: CubeDir) {
+        self.segments.push(segment);
+        self.next_direction = next_direction;
+        self.reindex();
+    }
+
+    /// The cached distances to the goal and the passengers, computed on
+    /// first use after the fields changed.
+    pub fn distance_map(&self) -> &DistanceMap {
+        self.distances.get_or_init(|| DistanceMap::new(self))
+    }
+
+    /// The cached distances to the spot the passenger at the given passenger
+    /// field can be picked up from, if it has passengers left.
+    pub fn passenger_distances(&self, coords: CubeVec) -> Option<&Distances> {
+        self.has_passengers_at(coords).then(|| self.distance_map().passenger(coords)).flatten()
+    }
+
+    /// The positions of the passenger fields that have passengers left.
+    pub fn passengers_left(&self) -> impl Iterator<Item = CubeVec> + '_ {
+        self.distance_map().passengers().filter(|&p| self.has_passengers_at(p))
+    }
+
+    /// The number of steps from the given position to the nearest spot a
+    /// passenger can be picked up from.
+    pub fn to_nearest_passenger(&self, coords: CubeVec) -> Option<u32> {
+        self.passengers_left().filter_map(|p| self.distance_map().passenger(p)?.get(coords)).min()
+    }
+
+    /// Whether there is a passenger field with passengers left at the given
+    /// position.
+    fn has_passengers_at(&self, coords: CubeVec) -> bool {
+        matches!(self.get(coords), Some(&Field::Passenger { passenger, .. }) if passenger > 0)
+    }
+
+    /// Rebuilds the field index and drops the cached distances.
+    fn reindex(&mut self) {
+        self.index = Arc::new(FieldIndex::new(&self.segments, self.next_direction));
+        self.invalidate_distances();
+    }
+
+    /// Drops the cached distances, which are recomputed on the next lookup.
+    fn invalidate_distances(&mut self) {
+        self.distances = Arc::default();
+    }
+
+    /// The layout of the positions on the board.
+    pub(crate) fn layout(&self) -> Layout {
+        self.index.layout()
+    }
+
     /// The bounding box of the segments as `(min_x..(max_x + 1), min_y..(max_y + 1))`.
     pub fn bounds(&self) -> (Range<i32>, Range<i32>) {
         self.segments.iter().fold(((0..0), (0..0)), |(xs, ys), segment| {
@@ -46,31 +200,33 @@ impl Board {
 
     /// Fetches the field at the given position.
     pub fn get(&self, coords: CubeVec) -> Option<&Field> {
-        self.segments.iter()
-            .filter(|s| coords.distance_to(s.center) <= 3)
-            .find_map(|s| s.get_global(coords))
+        let (i, x, y) = self.index.get(coords)?;
+        Some(&self.segments[i].fields[x][y])
     }
 
-    /// Fetches the field at the given position mutably.
+    /// Fetches the field at the given position mutably, dropping the cached
+    /// distances.
     pub fn get_mut(&mut self, coords: CubeVec) -> Option<&mut Field> {
-        self.segments.iter_mut()
-            .filter(|s| coords.distance_to(s.center) <= 3)
-            .find_map(|s| s.get_global_mut(coords))
+        self.invalidate_distances();
+        self.field_mut(coords)
+    }
+
+    /// Fetches the field at the given position mutably without touching the
+    /// cached distances.
+    fn field_mut(&mut self, coords: CubeVec) -> Option<&mut Field> {
+        let (i, x, y) = self.index.get(coords)?;
+        Some(&mut self.segments[i].fields[x][y])
     }
 
     /// Checks whether the field has a current.
     pub fn does_field_have_current(&self, coords: CubeVec) -> bool {
-        self.segment_with_index_at(coords)
-            .map(|(i, s)| {
-                let next_dir: CubeVec = self.segments.get(i + 1).map(|s| s.direction).unwrap_or(self.next_direction).into();
-                [
-                    s.center - s.direction,
-                    s.center,
-                    s.center + next_dir,
-                    s.center + 2 * next_dir,
-                ].contains(&coords)
-            })
-            .unwrap_or(false)
+        self.index.current(coords).is_some()
+    }
+
+    /// The direction the current flows in at the given coordinates, if there
+    /// is a current. It follows the segment and bends towards the next one.
+    pub fn current_direction_at(&self, coords: CubeVec) -> Option<CubeDir> {
+        self.index.current(coords)
     }
 
     /// Fetches the distance between two fields as the number of segments.
@@ -91,27 +247,48 @@ impl Board {
         ship.speed - (self.does_field_have_current(ship.position) as i32)
     }
 
-    /// Picks up a passenger.
-    pub fn pick_up_passenger(&mut self, ship: &mut Ship) -> bool {
-        if self.effective_speed(*ship) < 2 && self.remove_passenger_at(ship.position) {
+    /// Picks up a passenger, returning the position of the passenger field
+    /// it was picked up from.
+    pub fn pick_up_passenger(&mut self, ship: &mut Ship) -> Option<CubeVec> {
+        if self.effective_speed(*ship) < 2 {
+            let position = self.remove_passenger_at(ship.position)?;
             ship.passengers += 1;
-            true
+            Some(position)
         } else {
-            false
+            None
         }
     }
 
-    /// Removes a passenger at the given position.
-    fn remove_passenger_at(&mut self, coords: CubeVec) -> bool {
-        for d in CubeDir::ALL {
-            if let Some(Field::Passenger { direction, passenger }) = self.get_mut(coords + d) {
-                if *passenger > 0 && *direction == -d {
-                    *passenger -= 1;
-                    return true;
-                }
-            }
+    /// Puts a passenger back on the passenger field at the given position.
+    pub fn return_passenger(&mut self, coords: CubeVec) {
+        // Passenger fields block the way either way, so the cached distances
+        // stay valid
+        if let Some(Field::Passenger { passenger, .. }) = self.field_mut(coords) {
+            *passenger += 1;
+        }
+    }
+
+    /// The position of the passenger field a ship at the given position
+    /// would pick up a passenger from, if any.
+    pub fn passenger_next_to(&self, coords: CubeVec) -> Option<CubeVec> {
+        CubeDir::ALL.into_iter().find(|&d| {
+            matches!(self.get(coords + d), Some(&Field::Passenger { direction, passenger }) if passenger > 0 && direction == -d)
+        }).map(|d| coords + d)
+    }
+
+    /// Removes a passenger next to the given position, returning the position
+    /// of the passenger field.
+    fn remove_passenger_at(&mut self, coords: CubeVec) -> Option<CubeVec> {
+        let position = self.passenger_next_to(coords)?;
+        if let Some(Field::Passenger { passenger, .. }) = self.field_mut(position) {
+            *passenger -= 1;
         }
-        false
+        Some(position)
+    }
+
+    /// An iterator over all fields and their positions.
+    pub fn fields(&self) -> impl Iterator<Item = (CubeVec, Field)> + '_ {
+        self.segments.iter().flat_map(|s| s.global_positions().map(move |p| (p, s[p])))
     }
 
     /// Fetches the segment containing the given coordinates.
@@ -126,7 +303,8 @@ impl Board {
 
     /// Fetches the index of the segment containing the given coordinates.
     pub fn segment_with_index_at(&self, coords: CubeVec) -> Option<(usize, &Segment)> {
-        self.segments.iter().enumerate().find(|(_, s)| s.get_global(coords).is_some())
+        let (i, _, _) = self.index.get(coords)?;
+        Some((i, &self.segments[i]))
     }
 
     /// The advance points for the given ship.
@@ -141,6 +319,33 @@ impl Board {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    /// The number of boards cloned on this thread, for checking that
+    /// searching doesn't clone them.
+    pub(crate) static BOARD_CLONES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl Clone for Board {
+    fn clone(&self) -> Self {
+        #[cfg(test)]
+        BOARD_CLONES.with(|c| c.set(c.get() + 1));
+        Self {
+            segments: self.segments.clone(),
+            next_direction: self.next_direction,
+            index: self.index.clone(),
+            distances: self.distances.clone(),
+        }
+    }
+}
+
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        // The index and the distances are derived from the segments
+        self.segments == other.segments && self.next_direction == other.next_direction
+    }
+}
+
 impl Index<CubeVec> for Board {
     type Output = Field;
 
@@ -165,11 +370,143 @@ impl TryFrom<&Element> for Board {
     type Error = Error;
 
     fn try_from(elem: &Element) -> Result<Self> {
-        Ok(Self {
-            segments: elem.childs_by_name("segment")
+        Ok(Self::new(
+            elem.childs_by_name("segment")
                 .map(Segment::try_from)
                 .collect::<Result<Vec<Segment>>>()?,
-            next_direction: elem.attribute("nextDirection")?.parse()?,
-        })
+            elem.attribute("nextDirection")?.parse()?,
+        ))
+    }
+}
+
+impl From<Board> for Element {
+    fn from(board: Board) -> Self {
+        Element::new("board")
+            .attribute("nextDirection", board.next_direction)
+            .childs(board.segments.into_iter().map(Element::from))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::{Board, DistanceMap, Field, CubeDir, CubeVec, Rules, NUMBER_OF_SEGMENTS, NUMBER_OF_PASSENGERS, MIN_ISLANDS, MAX_ISLANDS, MIN_SANDBANKS, MAX_SANDBANKS}, util::assert_xml_roundtrip};
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        assert_eq!(Board::generate(42), Board::generate(42));
+        assert_ne!(Board::generate(1), Board::generate(2));
+    }
+
+    #[test]
+    fn test_generate_follows_rules() {
+        for seed in 0..200 {
+            let board = Board::generate(seed);
+            assert_eq!(board.segments.len(), NUMBER_OF_SEGMENTS);
+            assert_eq!(board.segments[0].direction, CubeDir::Right);
+            assert!(board.segments[0].fields.iter().flatten().all(|&f| f == Field::Water));
+
+            let mut passengers = 0;
+            for (i, segment) in board.segments.iter().enumerate() {
+                let count = |pred: fn(&Field) -> bool| segment.fields.iter().flatten().filter(|f| pred(f)).count();
+                if i > 0 {
+                    assert!((MIN_ISLANDS..=MAX_ISLANDS).contains(&count(|f| matches!(f, Field::Island))));
+                }
+                assert_eq!(count(|f| matches!(f, Field::Goal)), if i == NUMBER_OF_SEGMENTS - 1 { 3 } else { 0 });
+                passengers += count(|f| matches!(f, Field::Passenger { .. }));
+
+                for position in segment.global_positions() {
+                    assert_eq!(board.segment_index_at(position), Some(i), "Segments overlap at {} (seed {})", position, seed);
+                }
+            }
+            assert_eq!(passengers, NUMBER_OF_PASSENGERS);
+            assert_xml_roundtrip!(board.clone());
+
+            for segment in &board.segments {
+                for position in segment.global_positions() {
+                    if let Field::Passenger { direction, passenger } = segment[position] {
+                        assert_eq!(passenger, 1);
+                        assert_eq!(board.get(position + direction), Some(&Field::Water), "Passenger at {} has no dock (seed {})", position, seed);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_current_direction() {
+        let board = Board::generate(0);
+        for (position, _) in board.fields() {
+            assert_eq!(board.current_direction_at(position).is_some(), board.does_field_have_current(position));
+        }
+        let first = &board.segments[0];
+        assert_eq!(board.current_direction_at(first.center), Some(first.direction));
+        assert_eq!(board.current_direction_at(first.center + board.segments[1].direction), Some(board.segments[1].direction));
+    }
+
+    #[test]
+    fn test_edit_segments_reindexes() {
+        let mut board = Board::generate(0);
+        let last = board.segments()[NUMBER_OF_SEGMENTS - 1].center;
+        assert!(board.get(last).is_some());
+
+        let removed = board.edit_segments(|segments| segments.pop()).unwrap();
+        assert_eq!(removed.center, last);
+        assert_eq!(board.get(last), None);
+        assert_eq!(board.distance_map(), &DistanceMap::new(&board));
+    }
+
+    #[test]
+    fn test_index_matches_segments() {
+        for seed in 0..20 {
+            let board = Board::generate(seed);
+            for (i, segment) in board.segments.iter().enumerate() {
+                for position in segment.global_positions() {
+                    assert_eq!(board.get(position), segment.get_global(position));
+                    assert_eq!(board.segment_index_at(position), Some(i));
+                }
+            }
+            let outside = board.segments[0].center - CubeVec::from(CubeDir::Right) * 3;
+            assert_eq!(board.get(outside), None);
+            assert!(!board.does_field_have_current(outside));
+        }
+    }
+
+    #[test]
+    fn test_add_segment_extends_index() {
+        let full = Board::generate(5);
+        let mut board = Board::new(full.segments[..4].to_vec(), full.segments[4].direction);
+        let position = full.segments[4].center;
+        assert_eq!(board.get(position), None);
+        assert!(!board.does_field_have_current(position));
+
+        for segment in &full.segments[4..] {
+            let next_direction = full.segments.get(board.segments.len() + 1).map(|s| s.direction).unwrap_or(full.next_direction);
+            board.add_segment(segment.clone(), next_direction);
+        }
+        assert_eq!(board, full);
+        assert!(board.does_field_have_current(position));
+        board[position] = Field::Sandbank;
+        assert_eq!(board.segments[4].get_global(position), Some(&Field::Sandbank));
+    }
+
+    #[test]
+    fn test_generate_with_sandbanks() {
+        let sandbanks = |board: &Board| board.segments.iter()
+            .map(|s| s.fields.iter().flatten().filter(|&&f| f == Field::Sandbank).count())
+            .collect::<Vec<_>>();
+
+        let mut total = 0;
+        for seed in 0..50 {
+            assert!(sandbanks(&Board::generate(seed)).iter().all(|&c| c == 0));
+
+            let board = Board::generate_with_rules(seed, Rules::with_sandbanks());
+            let counts = sandbanks(&board);
+            assert_eq!(counts[0], 0);
+            assert!(counts.iter().all(|c| (MIN_SANDBANKS..=MAX_SANDBANKS).contains(c)));
+            assert_xml_roundtrip!(board.clone());
+            total += counts.iter().sum::<usize>();
+        }
+        assert!(total > 0);
     }
 }
@@ -24,5 +24,7 @@ pub const NUMBER_OF_SEGMENTS: usize = 8;
 // Board Fields
 pub const MAX_SPECIAL: usize = 0; // Sandbanks disabled
 pub const MIN_SPECIAL: usize = 0;
+pub const MAX_SANDBANKS: usize = 2; // Used instead when sandbanks are enabled
+pub const MIN_SANDBANKS: usize = 0;
 pub const MAX_ISLANDS: usize = 3;
 pub const MIN_ISLANDS: usize = 2;
@@ -45,6 +45,18 @@ impl CubeDir {
         Self::ALL[(self.turns() + turns).rem_euclid(Self::COUNT as i32) as usize]
     }
 
+    /// An arrow pointing in the direction.
+    pub fn arrow(self) -> char {
+        match self {
+            Self::Right => '→',
+            Self::DownRight => '↘',
+            Self::DownLeft => '↙',
+            Self::Left => '←',
+            Self::UpLeft => '↖',
+            Self::UpRight => '↗',
+        }
+    }
+
     /// The opposite direction if the given condition is satisfied.
     pub fn opposite_if(self, condition: bool) -> Self {
         if condition { -self } else { self }
@@ -93,6 +93,15 @@ impl CubeVec {
     pub fn hex_neighbors(self) -> [Self; 6] {
         CubeDir::ALL.map(|v| self + v)
     }
+
+    /// Converts the vector to an XML element with the given tag name.
+    pub fn to_element(self, name: &str) -> Element {
+        Element::new(name)
+            .attribute("q", self.q)
+            .attribute("r", self.r)
+            .attribute("s", self.s)
+            .build()
+    }
 }
 
 impl Add for CubeVec {
@@ -250,9 +259,15 @@ impl TryFrom<&Element> for CubeVec {
     }
 }
 
+impl From<CubeVec> for Element {
+    fn from(vec: CubeVec) -> Self {
+        vec.to_element("position")
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{util::assert_xml_parse, game::CubeVec};
+    use crate::{util::{assert_xml_parse, assert_xml_format, assert_xml_roundtrip}, game::CubeVec};
 
     #[test]
     fn test_xml_parses() {
@@ -261,4 +276,17 @@ mod tests {
             CubeVec::new(23, 0, -23)
         );
     }
+
+    #[test]
+    fn test_xml_formats() {
+        assert_xml_format!(
+            CubeVec::new(-2, 1, 1),
+            r#"<position q="-2" r="1" s="1" />"#
+        );
+    }
+
+    #[test]
+    fn test_xml_roundtrips() {
+        assert_xml_roundtrip!(CubeVec::new(12, -4, -8));
+    }
 }
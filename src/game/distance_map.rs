@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+use super::{Board, CubeDir, CubeVec, Field, Layout};
+
+/// Marks fields that can't be reached from any source.
+const UNREACHABLE: u8 = u8::MAX;
+
+/// The number of steps over empty fields from every field to the nearest
+/// of a set of source fields, with islands and passenger fields acting as
+/// walls. Lookups take constant time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Distances {
+    layout: Layout,
+    steps: Vec<u8>,
+}
+
+impl Distances {
+    /// Computes the distances to the nearest of the given positions.
+    pub fn new(board: &Board, sources: impl IntoIterator<Item = CubeVec>) -> Self {
+        let layout = board.layout();
+        let passable = Self::passable(board, layout);
+        Self::search(layout, &passable, sources)
+    }
+
+    /// The number of steps from the given position to the nearest source, if
+    /// there is a way there.
+    pub fn get(&self, coords: CubeVec) -> Option<u32> {
+        let steps = self.steps[self.layout.index(coords)?];
+        (steps != UNREACHABLE).then_some(steps as u32)
+    }
+
+    /// Which positions of the layout hold empty fields.
+    fn passable(board: &Board, layout: Layout) -> Vec<bool> {
+        let mut passable = vec![false; layout.len()];
+        for (position, field) in board.fields() {
+            passable[layout.index(position).unwrap()] = field.is_empty();
+        }
+        passable
+    }
+
+    /// Runs a breadth-first search from the given positions.
+    fn search(layout: Layout, passable: &[bool], sources: impl IntoIterator<Item = CubeVec>) -> Self {
+        let mut steps = vec![UNREACHABLE; layout.len()];
+        let mut queue = VecDeque::new();
+        for source in sources {
+            if let Some(i) = layout.index(source) {
+                steps[i] = 0;
+                queue.push_back(source);
+            }
+        }
+
+        while let Some(position) = queue.pop_front() {
+            let next = steps[layout.index(position).unwrap()] + 1;
+            for direction in CubeDir::ALL {
+                let neighbor = position + direction;
+                if let Some(i) = layout.index(neighbor) {
+                    if passable[i] && steps[i] == UNREACHABLE {
+                        steps[i] = next;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        Self { layout, steps }
+    }
+}
+
+/// The distances from every field to the goal and to the spots passengers
+/// can be picked up from, as cached by the board. Picking up passengers
+/// doesn't change them, see `Board::passenger_distances` for the passenger
+/// fields that have passengers left.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DistanceMap {
+    goal: Distances,
+    /// The distances to the dock of each passenger field by its position.
+    passengers: Vec<(CubeVec, Distances)>,
+}
+
+impl DistanceMap {
+    /// Computes the distances on the given board.
+    pub fn new(board: &Board) -> Self {
+        let layout = board.layout();
+        let passable = Distances::passable(board, layout);
+        let goals = board.fields().filter(|&(_, f)| f == Field::Goal).map(|(p, _)| p);
+        let goal = Distances::search(layout, &passable, goals);
+        let passengers = board.fields()
+            .filter_map(|(p, f)| match f {
+                Field::Passenger { direction, .. } => Some((p, Distances::search(layout, &passable, [p + direction]))),
+                _ => None,
+            })
+            .collect();
+        Self { goal, passengers }
+    }
+
+    /// The distances to the nearest goal field.
+    pub fn goal(&self) -> &Distances {
+        &self.goal
+    }
+
+    /// The distances to the spot the passenger at the given passenger field
+    /// can be picked up from, whether or not it has passengers left.
+    pub fn passenger(&self, position: CubeVec) -> Option<&Distances> {
+        self.passengers.iter().find(|&&(p, _)| p == position).map(|(_, d)| d)
+    }
+
+    /// The positions of the passenger fields.
+    pub fn passengers(&self) -> impl Iterator<Item = CubeVec> + '_ {
+        self.passengers.iter().map(|&(p, _)| p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Board, CubeDir, CubeVec, Field, Rules, Ship, NUMBER_OF_PASSENGERS};
+
+    use super::{DistanceMap, Distances};
+
+    #[test]
+    fn test_goal_distances() {
+        for seed in 0..20 {
+            let board = Board::generate_with_rules(seed, Rules::with_sandbanks());
+            let distances = board.distance_map().goal();
+            for (position, field) in board.fields() {
+                let steps = distances.get(position);
+                match field {
+                    Field::Goal => assert_eq!(steps, Some(0)),
+                    Field::Island => assert_eq!(steps, None),
+                    _ if steps == Some(0) => panic!("{} is no goal but at distance 0 (seed {})", position, seed),
+                    _ => {},
+                }
+                // Neighboring empty fields are at most one step apart
+                if let Some(steps) = steps.filter(|_| field.is_empty()) {
+                    for neighbor in CubeDir::ALL.map(|d| position + d).into_iter().filter(|&p| board.is_empty_at(p)) {
+                        assert!(distances.get(neighbor).unwrap().abs_diff(steps) <= 1);
+                    }
+                }
+            }
+            assert!(distances.get(board.segments()[0].center).unwrap() >= 3 * 4);
+            assert_eq!(distances.get(CubeVec::new(100, 0, -100)), None);
+        }
+    }
+
+    #[test]
+    fn test_islands_are_walls() {
+        let board = Board::generate(0);
+        let center = board.segments()[0].center;
+        let open = Distances::new(&board, [center]);
+        assert_eq!(open.get(center + CubeDir::Right + CubeDir::Right), Some(2));
+
+        let mut walled = board.clone();
+        walled[center + CubeDir::Right] = Field::Island;
+        walled[center + CubeDir::DownRight] = Field::Island;
+        walled[center + CubeDir::UpRight] = Field::Island;
+        let around = Distances::new(&walled, [center]);
+        assert_eq!(around.get(center + CubeDir::Right), None);
+        assert_eq!(around.get(center + CubeDir::Right + CubeDir::Right), Some(5));
+    }
+
+    #[test]
+    fn test_passenger_distances_follow_pick_ups() {
+        let board = Board::generate(3);
+        let map = board.distance_map();
+        assert_eq!(map.passengers().count(), NUMBER_OF_PASSENGERS);
+        for passenger in map.passengers() {
+            let Field::Passenger { direction, .. } = board[passenger] else { panic!("No passenger at {}", passenger) };
+            assert_eq!(map.passenger(passenger).unwrap().get(passenger + direction), Some(0));
+            assert_eq!(board.passenger_distances(passenger), map.passenger(passenger));
+            assert!(board.to_nearest_passenger(passenger + direction) == Some(0));
+        }
+        assert_eq!(map, &DistanceMap::new(&board));
+
+        // Picking up the passenger hides its distances, taking the move back
+        // shows them again, all without touching the shared distances
+        let passenger = map.passengers().next().unwrap();
+        let Field::Passenger { direction, .. } = board[passenger] else { unreachable!() };
+        let mut ship = Ship { position: passenger + direction, ..Default::default() };
+        let mut picked = board.clone();
+        assert_eq!(picked.pick_up_passenger(&mut ship), Some(passenger));
+        assert_eq!(picked.passenger_distances(passenger), None);
+        assert_eq!(picked.passengers_left().count(), NUMBER_OF_PASSENGERS - 1);
+        assert!(std::ptr::eq(picked.distance_map(), map));
+        assert_eq!(picked.distance_map(), &DistanceMap::new(&picked));
+        picked.return_passenger(passenger);
+        assert_eq!(&picked, &board);
+        assert_eq!(picked.passengers_left().count(), NUMBER_OF_PASSENGERS);
+        assert!(std::ptr::eq(picked.distance_map(), map));
+    }
+
+    #[test]
+    fn test_writes_drop_distances() {
+        let mut board = Board::generate(0);
+        let goal = board.fields().find(|&(_, f)| f == Field::Goal).unwrap().0;
+        assert_eq!(board.distance_map().goal().get(goal), Some(0));
+
+        board[goal] = Field::Island;
+        assert_eq!(board.distance_map().goal().get(goal), None);
+        assert_eq!(board.distance_map(), &DistanceMap::new(&board));
+    }
+}
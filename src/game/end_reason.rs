@@ -0,0 +1,27 @@
+use std::fmt;
+
+use super::Team;
+
+/// The reason why a game ended regularly, as reported by `State::end_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndReason {
+    /// A ship with two passengers reached a goal field at the end of a round.
+    GoalReached,
+    /// The ship of the given team fell more than 3 segments behind.
+    TooFarBehind(Team),
+    /// The round limit has been reached.
+    RoundLimit,
+    /// Neither player can move.
+    NoMoves,
+}
+
+impl fmt::Display for EndReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GoalReached => write!(f, "A ship reached the goal"),
+            Self::TooFarBehind(team) => write!(f, "Team {} fell too far behind", team),
+            Self::RoundLimit => write!(f, "The round limit was reached"),
+            Self::NoMoves => write!(f, "Neither player can move"),
+        }
+    }
+}
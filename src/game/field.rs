@@ -39,9 +39,24 @@ impl TryFrom<&Element> for Field {
     }
 }
 
+impl From<Field> for Element {
+    fn from(field: Field) -> Self {
+        match field {
+            Field::Water => Element::new("water").build(),
+            Field::Island => Element::new("island").build(),
+            Field::Passenger { direction, passenger } => Element::new("passenger")
+                .attribute("direction", direction)
+                .attribute("passenger", passenger)
+                .build(),
+            Field::Goal => Element::new("goal").build(),
+            Field::Sandbank => Element::new("sandbank").build(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{util::assert_xml_parse, game::Field};
+    use crate::{util::{assert_xml_parse, assert_xml_format, assert_xml_roundtrip}, game::{Field, CubeDir}};
 
     #[test]
     fn test_xml_parses() {
@@ -50,4 +65,21 @@ mod tests {
         assert_xml_parse!(r#"<goal />"#, Field::Goal);
         assert_xml_parse!(r#"<sandbank />"#, Field::Sandbank);
     }
+
+    #[test]
+    fn test_xml_formats() {
+        assert_xml_format!(
+            Field::Passenger { direction: CubeDir::UpRight, passenger: 1 },
+            r#"<passenger direction="UP_RIGHT" passenger="1" />"#
+        );
+    }
+
+    #[test]
+    fn test_xml_roundtrips() {
+        assert_xml_roundtrip!(Field::Water);
+        assert_xml_roundtrip!(Field::Island);
+        assert_xml_roundtrip!(Field::Passenger { direction: CubeDir::Left, passenger: 0 });
+        assert_xml_roundtrip!(Field::Goal);
+        assert_xml_roundtrip!(Field::Sandbank);
+    }
 }
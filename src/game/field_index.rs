@@ -0,0 +1,107 @@
+use super::{CubeDir, CubeVec, Segment};
+
+/// A rectangle of positions laid out densely by their q and r coordinates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Layout {
+    min_q: i32,
+    min_r: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Layout {
+    /// The smallest layout covering all given positions.
+    fn covering(positions: impl Iterator<Item = CubeVec>) -> Self {
+        let bounds = positions.fold(None, |bounds, p| Some(match bounds {
+            Some((min_q, min_r, max_q, max_r)) => (p.q().min(min_q), p.r().min(min_r), p.q().max(max_q), p.r().max(max_r)),
+            None => (p.q(), p.r(), p.q(), p.r()),
+        }));
+        match bounds {
+            Some((min_q, min_r, max_q, max_r)) => Self { min_q, min_r, width: max_q - min_q + 1, height: max_r - min_r + 1 },
+            None => Self::default(),
+        }
+    }
+
+    /// The number of positions covered.
+    pub(crate) fn len(self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    /// The index of the given position, if covered.
+    pub(crate) fn index(self, coords: CubeVec) -> Option<usize> {
+        let (q, r) = (coords.q() - self.min_q, coords.r() - self.min_r);
+        ((0..self.width).contains(&q) && (0..self.height).contains(&r)).then_some((q * self.height + r) as usize)
+    }
+}
+
+/// Where a field is stored, packed to keep boards cheap to clone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    segment: u8,
+    x: u8,
+    y: u8,
+    /// The direction of the current as an index into `CubeDir::ALL`, or
+    /// `NO_CURRENT`.
+    current: u8,
+}
+
+/// Marks positions without a field.
+const NO_SEGMENT: u8 = u8::MAX;
+/// Marks fields without a current.
+const NO_CURRENT: u8 = u8::MAX;
+
+impl Entry {
+    const EMPTY: Self = Self { segment: NO_SEGMENT, x: 0, y: 0, current: NO_CURRENT };
+}
+
+/// An index from global positions to the segments and array positions of
+/// the fields, along with their currents, for constant-time lookups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FieldIndex {
+    layout: Layout,
+    entries: Vec<Entry>,
+}
+
+impl FieldIndex {
+    /// Builds the index for the given segments. Where segments overlap, the
+    /// earlier one wins.
+    pub(crate) fn new(segments: &[Segment], next_direction: CubeDir) -> Self {
+        let layout = Layout::covering(segments.iter().flat_map(|s| s.global_positions()));
+        let mut entries = vec![Entry::EMPTY; layout.len()];
+        for (i, segment) in segments.iter().enumerate() {
+            let next_dir = segments.get(i + 1).map(|s| s.direction).unwrap_or(next_direction);
+            let currents = [
+                (segment.center - segment.direction, segment.direction),
+                (segment.center, segment.direction),
+                (segment.center + next_dir, next_dir),
+                (segment.center + 2 * CubeVec::from(next_dir), next_dir),
+            ];
+            for (x, y, position) in segment.indexed_positions() {
+                let entry = &mut entries[layout.index(position).unwrap()];
+                if entry.segment == NO_SEGMENT {
+                    let current = currents.iter().find(|&&(p, _)| p == position).map(|&(_, d)| d as u8).unwrap_or(NO_CURRENT);
+                    *entry = Entry { segment: i as u8, x: x as u8, y: y as u8, current };
+                }
+            }
+        }
+        Self { layout, entries }
+    }
+
+    /// The layout of the indexed positions.
+    pub(crate) fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The segment index and array position of the field at the given
+    /// position.
+    pub(crate) fn get(&self, coords: CubeVec) -> Option<(usize, usize, usize)> {
+        let entry = self.entries[self.layout.index(coords)?];
+        (entry.segment != NO_SEGMENT).then_some((entry.segment as usize, entry.x as usize, entry.y as usize))
+    }
+
+    /// The direction of the current at the given position, if any.
+    pub(crate) fn current(&self, coords: CubeVec) -> Option<CubeDir> {
+        let entry = self.entries[self.layout.index(coords)?];
+        (entry.current != NO_CURRENT).then(|| CubeDir::ALL[entry.current as usize])
+    }
+}
@@ -11,6 +11,21 @@ pub enum MoveMistake {
     ActionFailed(ActionProblem),
 }
 
+impl MoveMistake {
+    /// The name of the kind of mistake, without any details.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NoActions => "NoActions",
+            Self::PushActionRequired => "PushActionRequired",
+            Self::SandbankEnd => "SandbankEnd",
+            Self::FirstActionAccelerate => "FirstActionAccelerate",
+            Self::MovementPointsLeft(_) => "MovementPointsLeft",
+            Self::MovementPointsMissing(_) => "MovementPointsMissing",
+            Self::ActionFailed(_) => "ActionFailed",
+        }
+    }
+}
+
 impl From<ActionProblem> for MoveMistake {
     fn from(value: ActionProblem) -> Self {
         MoveMistake::ActionFailed(value)
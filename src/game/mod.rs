@@ -3,23 +3,42 @@ mod board;
 mod constants;
 mod cube_dir;
 mod cube_vec;
+mod distance_map;
+mod end_reason;
 mod field;
+mod field_index;
 mod mistake;
 mod r#move;
+mod pathfinding;
+mod perft;
+mod render;
+mod rules;
 mod segment;
 mod ship;
 mod state;
+mod svg;
 mod team;
+mod validation;
+mod zobrist;
 
 pub use action::*;
 pub use board::*;
 pub use constants::*;
 pub use cube_dir::*;
 pub use cube_vec::*;
+pub use distance_map::*;
+pub use end_reason::*;
 pub use field::*;
+pub(crate) use field_index::*;
 pub use mistake::*;
 pub use r#move::*;
+pub use pathfinding::*;
+pub use perft::*;
+pub use render::*;
+pub use rules::*;
 pub use ship::*;
 pub use segment::*;
 pub use state::*;
+pub use svg::*;
 pub use team::*;
+pub use validation::*;
@@ -1,6 +1,6 @@
 //! Ported from https://github.com/software-challenge/backend/blob/be88340f619892fe70c4cbd45e131d5445e883c7/plugin/src/main/kotlin/sc/plugin2024/Field.kt
 
-use std::convert::Infallible;
+use std::{convert::Infallible, fmt};
 
 use crate::util::{Error, Element, Result, Perform};
 
@@ -46,16 +46,36 @@ impl Move {
 
     /// Coalesces advances.
     pub fn coalesced(&self) -> Move {
-        let mut actions = Vec::with_capacity(self.actions.capacity());
-        for action in &self.actions {
-            if let (Some(Action::Advance(last)), Action::Advance(current)) = (actions.last(), action) {
-                let count = actions.len();
-                actions[count - 1] = Action::Advance(*last + *current);
+        self.coalesced_with_indices().into_iter().map(|(_, a)| a).collect()
+    }
+
+    /// Coalesces advances, pairing each resulting action with the index of
+    /// the first action it was merged from.
+    pub fn coalesced_with_indices(&self) -> Vec<(usize, Action)> {
+        let mut actions: Vec<(usize, Action)> = Vec::with_capacity(self.actions.len());
+        for (i, action) in self.actions.iter().enumerate() {
+            if let (Some((_, Action::Advance(last))), Action::Advance(current)) = (actions.last_mut(), action) {
+                *last += *current;
             } else {
-                actions.push(*action);
+                actions.push((i, *action));
+            }
+        }
+        actions
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "(no actions)");
+        }
+        for (i, action) in self.actions.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
             }
+            write!(f, "{}", action)?;
         }
-        Move { actions }
+        Ok(())
     }
 }
 
@@ -118,6 +138,32 @@ mod tests {
 
     use crate::{util::{assert_xml_format, assert_xml_roundtrip}, game::{Move, CubeDir, Action}};
 
+    #[test]
+    fn test_coalesced() {
+        let m = Move {
+            actions: vec![
+                Action::accelerate(1),
+                Action::advance(1),
+                Action::advance(2),
+                Action::turn(CubeDir::DownRight),
+                Action::advance(1),
+            ]
+        };
+        assert_eq!(m.coalesced_with_indices(), vec![
+            (0, Action::accelerate(1)),
+            (1, Action::advance(3)),
+            (3, Action::turn(CubeDir::DownRight)),
+            (4, Action::advance(1)),
+        ]);
+        assert_eq!(m.coalesced(), Move::from_iter(m.coalesced_with_indices().into_iter().map(|(_, a)| a)));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Move::new().to_string(), "(no actions)");
+        assert_eq!(Move::from_iter([Action::accelerate(1), Action::turn(CubeDir::DownRight), Action::advance(2)]).to_string(), "Accelerate +1, Turn DOWN_RIGHT, Advance 2");
+    }
+
     #[test]
     fn test_xml_formats() {
         assert_xml_format!(
@@ -0,0 +1,546 @@
+use std::{borrow::Cow, cmp::Reverse, collections::{BinaryHeap, HashMap}};
+
+use arrayvec::ArrayVec;
+
+use super::{Action, Board, CubeDir, CubeVec, Distances, Field, Move, Ship, State, FREE_ACC, MAX_SPEED, MIN_SPEED, ROUND_LIMIT};
+
+/// A destination for the path planner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// Ending a move on the given field.
+    Field(CubeVec),
+    /// Picking up a passenger from the passenger field at the given position.
+    Passenger(CubeVec),
+    /// Ending a move on a goal field slowly enough to finish there.
+    Goal,
+}
+
+impl Target {
+    /// Whether the given ship has reached the target at the end of a move.
+    fn is_reached(self, board: &Board, ship: Ship) -> bool {
+        match self {
+            Self::Field(position) => ship.position == position,
+            Self::Passenger(position) => {
+                board.effective_speed(ship) < 2 && board.passenger_next_to(ship.position) == Some(position)
+            },
+            Self::Goal => matches!(board.get(ship.position), Some(Field::Goal)) && board.effective_speed(ship) < 2,
+        }
+    }
+
+    /// The distances to the positions a ship has to end up on to reach the
+    /// target, or `None` if there are no such positions.
+    fn distances(self, board: &Board) -> Option<Cow<'_, Distances>> {
+        match self {
+            Self::Field(position) => board.is_empty_at(position).then(|| Cow::Owned(Distances::new(board, [position]))),
+            Self::Passenger(position) => board.passenger_distances(position).map(Cow::Borrowed),
+            Self::Goal => Some(Cow::Borrowed(board.distance_map().goal())),
+        }
+    }
+
+    /// Whether the target requires the ship to be slow enough to pick up a
+    /// passenger at the end.
+    fn requires_slow_end(self) -> bool {
+        !matches!(self, Self::Field(_))
+    }
+}
+
+/// Lower bounds on the turns a ship needs to cover a distance, found by
+/// relaxing the game to an open sea where the ship always moves as far as
+/// its speed allows. They are exact in the relaxed game and the real moves
+/// are possible there too, so the bounds never drop by more than one per
+/// turn, which keeps the search optimal.
+struct Bounds {
+    /// The farthest distance coverable in a number of turns, by turns,
+    /// starting speed and coal.
+    distances: Vec<Vec<Vec<i32>>>,
+}
+
+impl Bounds {
+    /// Computes the bounds up to the given number of turns and amount of coal.
+    /// With `slow_end`, the ship has to end at a speed of at most 2, while
+    /// `sandbanks` allows slowing down to 1 at the end of any turn.
+    fn new(max_turns: usize, max_coal: i32, slow_end: bool, sandbanks: bool) -> Self {
+        let speeds = MIN_SPEED..=MAX_SPEED;
+        let end = |speed: i32| if !slow_end || speed <= 2 { 0 } else { i32::MIN };
+        let mut distances = vec![speeds.clone().map(|v| vec![end(v); max_coal as usize + 1]).collect::<Vec<_>>()];
+
+        for k in 1..=max_turns {
+            let previous = &distances[k - 1];
+            let row = speeds.clone().map(|v| (0..=max_coal).map(|c| {
+                speeds.clone()
+                    .filter(|&w| (w - v).abs() - FREE_ACC <= c)
+                    .flat_map(|w| {
+                        let coal = (c - ((w - v).abs() - FREE_ACC).max(0)) as usize;
+                        let stay = previous[(w - MIN_SPEED) as usize][coal];
+                        let stranded = if sandbanks { previous[0][coal] } else { i32::MIN };
+                        [stay, stranded].map(|d| d.saturating_add(w))
+                    })
+                    .max()
+                    .unwrap_or(i32::MIN)
+            }).collect()).collect();
+            distances.push(row);
+        }
+
+        Self { distances }
+    }
+
+    /// The fewest turns the ship needs to cover the distance, if possible
+    /// within the computed turns.
+    fn turns(&self, ship: Ship, distance: u32) -> Option<usize> {
+        let (speed, coal) = ((ship.speed - MIN_SPEED) as usize, ship.coal.max(0) as usize);
+        self.distances.iter().position(|d| d[speed][coal] >= distance as i32)
+    }
+}
+
+/// A shortest sequence of moves for a ship to reach a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    /// The moves of the ship, one per turn of its team.
+    pub moves: Vec<Move>,
+    /// The coal used along the path.
+    pub coal: i32,
+}
+
+impl Path {
+    /// The number of own turns needed to reach the target.
+    pub fn turns(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+/// A ship reached at the end of a move during the search.
+struct Node {
+    ship: Ship,
+    parent: Option<usize>,
+    m: Option<Move>,
+    reached: bool,
+}
+
+/// Finds the path for the current ship that reaches the given target in the
+/// fewest turns, using the least coal among those. The moves account for
+/// currents, sandbanks, speed limits, free turns and free acceleration. The
+/// other ship is treated as an obstacle that stays where it is, so the path
+/// may have to be replanned once it moved. Returns `None` if the target
+/// can't be reached before the round limit.
+pub fn shortest_path(state: &State, target: Target) -> Option<Path> {
+    let board = state.board();
+    let start = state.current_ship();
+    let max_turns = ROUND_LIMIT.saturating_sub(state.turn() / 2);
+    let mut planner = Planner::new(board, state.other_ship().position);
+
+    let distances = target.distances(board)?;
+    let sandbanks = board.fields().any(|(_, f)| f == Field::Sandbank);
+    let bounds = Bounds::new(max_turns, start.coal, target.requires_slow_end(), sandbanks);
+    let estimate = |ship: Ship| bounds.turns(ship, distances.get(ship.position)?);
+
+    let mut nodes = vec![Node { ship: start, parent: None, m: None, reached: target.is_reached(board, start) }];
+    // Ordered by the estimated total turns, then coal used, then insertion
+    // for determinism
+    let mut queue = BinaryHeap::from([Reverse((estimate(start)?, 0, 0, 0))]);
+    // The fewest turns each ship was queued with
+    let mut queued: HashMap<(CubeVec, CubeDir, i32, i32), usize> = HashMap::from([((start.position, start.direction, start.speed, start.coal), 0)]);
+    // The turns and coal left with which each position, direction and speed
+    // was expanded. Nodes aren't expanded in the order of their turns, since
+    // the estimate depends on the coal, so only arriving with at least as
+    // many turns and at most as much coal as before is pointless.
+    let mut expanded: HashMap<(CubeVec, CubeDir, i32), Vec<(usize, i32)>> = HashMap::new();
+
+    while let Some(Reverse((_, coal, turns, index))) = queue.pop() {
+        let ship = nodes[index].ship;
+        if nodes[index].reached {
+            return Some(path_to(nodes, index, coal));
+        }
+        let expansions = expanded.entry((ship.position, ship.direction, ship.speed)).or_default();
+        if expansions.iter().any(|&(t, c)| t <= turns && c >= ship.coal) {
+            continue;
+        }
+        expansions.retain(|&(t, c)| t < turns || c > ship.coal);
+        expansions.push((turns, ship.coal));
+
+        let moves = planner.moves(ship, |next| {
+            let estimate = estimate(next).filter(|e| turns + 1 + e <= max_turns)?;
+            queued.get(&(next.position, next.direction, next.speed, next.coal))
+                .is_none_or(|&fewest| turns + 1 < fewest)
+                .then_some(estimate)
+        });
+        for (m, next, estimate) in moves {
+            queued.insert((next.position, next.direction, next.speed, next.coal), turns + 1);
+            queue.push(Reverse((turns + 1 + estimate, start.coal - next.coal, turns + 1, nodes.len())));
+            nodes.push(Node { ship: next, parent: Some(index), m: Some(m), reached: target.is_reached(board, next) });
+        }
+    }
+
+    None
+}
+
+/// Collects the moves leading to the node at the given index.
+fn path_to(mut nodes: Vec<Node>, index: usize, coal: i32) -> Path {
+    let mut moves = Vec::new();
+    let mut current = Some(index);
+    while let Some(i) = current {
+        moves.extend(nodes[i].m.take());
+        current = nodes[i].parent;
+    }
+    moves.reverse();
+    Path { moves, coal }
+}
+
+/// A field reached by advancing.
+#[derive(Debug, Clone, Copy)]
+struct Reach {
+    distance: i32,
+    cost: i32,
+    position: CubeVec,
+    sandbank: bool,
+}
+
+/// A ship in the middle of a move, with the index of the last action in
+/// the action tree.
+#[derive(Debug, Clone, Copy)]
+struct Partial {
+    ship: Ship,
+    last: Option<usize>,
+}
+
+/// The farthest a ship can get from its position within a turn.
+const REACH: i32 = MAX_SPEED;
+/// The number of fields within reach, in cube coordinates relative to the start.
+const REACH_COUNT: usize = ((2 * REACH + 1) * (2 * REACH + 1)) as usize;
+/// The number of distinct intermediate ships within a turn at a given speed,
+/// which are identified by position relative to the start, direction,
+/// movement points left, free turns (of which there are rarely more than
+/// three) and whether the last action was a turn.
+const PROGRESS_COUNT: usize = REACH_COUNT * CubeDir::COUNT * (MAX_SPEED as usize + 1) * 4 * 2;
+/// The number of distinct ships at the end of a move, which are identified
+/// by position relative to the start, direction and speed.
+const END_COUNT: usize = REACH_COUNT * CubeDir::COUNT * (MAX_SPEED as usize + 1);
+
+/// Generates the moves of a single ship on a fixed board.
+struct Planner<'a> {
+    board: &'a Board,
+    obstacle: CubeVec,
+    /// The fields reachable by advancing at full speed, by start and
+    /// direction. Looking up fields is comparatively slow, so these are cached.
+    reaches: HashMap<(CubeVec, CubeDir), Vec<Reach>>,
+    /// The exploration in which each intermediate ship was last visited,
+    /// which avoids clearing the array between explorations.
+    visited: Vec<u32>,
+    generation: u32,
+    /// The call in which a move last ended with each ship.
+    ended: Vec<u32>,
+    call: u32,
+}
+
+impl<'a> Planner<'a> {
+    /// Creates a planner treating the field at `obstacle` as blocked.
+    fn new(board: &'a Board, obstacle: CubeVec) -> Self {
+        Self { board, obstacle, reaches: HashMap::new(), visited: vec![0; PROGRESS_COUNT], generation: 0, ended: vec![0; END_COUNT], call: 0 }
+    }
+
+    /// Fetches the moves of the given ship along with the ship at the end of
+    /// each move, ready for its next turn, and the value `accept` returns for
+    /// that ship. Moves it returns `None` for are dropped before building
+    /// them. The partial moves are explored with the most coal left first,
+    /// and only the first one passing through each intermediate ship is kept,
+    /// which makes this much faster than trying every interleaving of the
+    /// actions. Pushes aren't generated.
+    fn moves<T>(&mut self, ship: Ship, mut accept: impl FnMut(Ship) -> Option<T>) -> Vec<(Move, Ship, T)> {
+        self.call += 1;
+        let board = self.board;
+        // The actions of all partial moves as a tree of (parent, action) pairs
+        let mut tree: Vec<(Option<usize>, Action)> = Vec::new();
+        let mut ends = Vec::new();
+
+        if board.is_sandbank_at(ship.position) {
+            // Ships on a sandbank may only advance one field forward or backward
+            for distance in [1, -1] {
+                let position = ship.position + ship.direction.opposite_if(distance < 0);
+                if board.is_empty_at(position) && position != self.obstacle && !board.does_field_have_current(position) {
+                    tree.push((None, Action::advance(distance)));
+                    ends.push(Partial { ship: Ship { position, movement: 0, ..ship }, last: Some(tree.len() - 1) });
+                }
+            }
+        } else {
+            // The speed only changes on sandbanks, where the move ends, so
+            // each speed can be explored on its own
+            for acc in (MIN_SPEED - ship.speed)..=(MAX_SPEED - ship.speed) {
+                let coal = ship.coal - (acc.abs() - ship.free_acc).max(0);
+                if coal >= 0 {
+                    let last = (acc != 0).then(|| {
+                        tree.push((None, Action::accelerate(acc)));
+                        tree.len() - 1
+                    });
+                    self.explore(ship.position, Partial { ship: Ship { coal, ..ship.accelerated(acc) }, last }, &mut tree, &mut ends);
+                }
+            }
+        }
+
+        // Keep the first, i.e. cheapest, move for each ship and prepare the
+        // ships for their next turn
+        let mut moves = Vec::new();
+        for partial in ends {
+            let end = Ship { movement: partial.ship.speed, free_acc: FREE_ACC, free_turns: 1, ..partial.ship };
+            let index = Self::end_index(ship.position, end);
+            if self.ended[index] == self.call {
+                continue;
+            }
+            self.ended[index] = self.call;
+            if let Some(value) = accept(end) {
+                let mut actions = Vec::new();
+                let mut current = partial.last;
+                while let Some(i) = current {
+                    actions.push(tree[i].1);
+                    current = tree[i].0;
+                }
+                actions.reverse();
+                moves.push((Move { actions }, end, value));
+            }
+        }
+        moves
+    }
+
+    /// Explores the partial moves following the given one, collecting those
+    /// that used up the movement points in `ends`. Partial moves are explored
+    /// with the most coal left first, so those through an intermediate ship
+    /// that was already visited can't leave more coal and are skipped.
+    fn explore(&mut self, start: CubeVec, partial: Partial, tree: &mut Vec<(Option<usize>, Action)>, ends: &mut Vec<Partial>) {
+        let board = self.board;
+        self.generation += 1;
+        // The partial moves by the coal left
+        let mut buckets: Vec<Vec<Partial>> = vec![Vec::new(); partial.ship.coal as usize + 1];
+        buckets[partial.ship.coal as usize].push(partial);
+
+        while let Some(partial) = buckets.iter_mut().rev().find_map(|b| b.pop()) {
+            let s = partial.ship;
+            let last = partial.last.map(|i| tree[i].1);
+            let turned = matches!(last, Some(Action::Turn(_)));
+            let index = Self::progress_index(start, s, turned);
+            if self.visited[index] == self.generation {
+                continue;
+            }
+            self.visited[index] = self.generation;
+            if s.movement == 0 && matches!(last, Some(Action::Turn(_) | Action::Advance(_))) {
+                ends.push(partial);
+                if board.is_sandbank_at(s.position) {
+                    continue;
+                }
+            }
+
+            if !turned {
+                for turns in [1i32, -1, 2, -2, 3] {
+                    let coal = s.coal - (turns.abs() - s.free_turns).max(0);
+                    let direction = s.direction.rotated_by(turns);
+                    let next = Ship { direction, coal, free_turns: (s.free_turns - turns.abs()).max(0), ..s };
+                    if coal >= 0 && self.visited[Self::progress_index(start, next, true)] != self.generation {
+                        tree.push((partial.last, Action::turn(direction)));
+                        buckets[coal as usize].push(Partial { ship: next, last: Some(tree.len() - 1) });
+                    }
+                }
+            }
+
+            if s.movement > 0 && !matches!(last, Some(Action::Advance(_))) {
+                let reaches: ArrayVec<Reach, { MAX_SPEED as usize }> = self.reaches(s.position, s.direction).iter()
+                    .copied()
+                    .take_while(|r| r.cost <= s.movement)
+                    .collect();
+                for reach in reaches {
+                    let next = if reach.sandbank {
+                        // Running onto a sandbank slows the ship down and ends its movement
+                        Ship { position: reach.position, speed: 1, movement: 0, ..s }
+                    } else {
+                        Ship { position: reach.position, movement: s.movement - reach.cost, ..s }
+                    };
+                    if self.visited[Self::progress_index(start, next, false)] != self.generation {
+                        tree.push((partial.last, Action::advance(reach.distance)));
+                        buckets[s.coal as usize].push(Partial { ship: next, last: Some(tree.len() - 1) });
+                    }
+                }
+            }
+        }
+    }
+
+    /// The index of the given intermediate ship in `visited`, ignoring its
+    /// speed, which doesn't change while exploring.
+    fn progress_index(start: CubeVec, ship: Ship, turned: bool) -> usize {
+        let offset = ship.position - start;
+        [
+            (offset.q() + REACH, 2 * REACH + 1),
+            (offset.r() + REACH, 2 * REACH + 1),
+            (ship.direction as i32, CubeDir::COUNT as i32),
+            (ship.movement, MAX_SPEED + 1),
+            (ship.free_turns.min(3), 4),
+            (turned as i32, 2),
+        ].into_iter().fold(0, |index, (value, count)| index * count + value) as usize
+    }
+
+    /// The index of the given ship at the end of a move in `ended`.
+    fn end_index(start: CubeVec, ship: Ship) -> usize {
+        let offset = ship.position - start;
+        [
+            (offset.q() + REACH, 2 * REACH + 1),
+            (offset.r() + REACH, 2 * REACH + 1),
+            (ship.direction as i32, CubeDir::COUNT as i32),
+            (ship.speed, MAX_SPEED + 1),
+        ].into_iter().fold(0, |index, (value, count)| index * count + value) as usize
+    }
+
+    /// Fetches the fields the ship can advance to in the given direction
+    /// with the most movement points. Entering a current costs an extra
+    /// movement point once per advance, and a ship with fewer movement
+    /// points gets exactly as far as the affordable costs.
+    fn reaches(&mut self, start: CubeVec, direction: CubeDir) -> &[Reach] {
+        let (board, obstacle) = (self.board, self.obstacle);
+        self.reaches.entry((start, direction)).or_insert_with(|| {
+            let mut reaches = Vec::new();
+            let mut position = start;
+            let mut cost = 0;
+            let mut has_current = false;
+
+            while cost < MAX_SPEED {
+                position += direction;
+                cost += 1;
+                if !board.is_empty_at(position) || position == obstacle {
+                    break;
+                }
+                if !has_current && board.does_field_have_current(position) {
+                    has_current = true;
+                    cost += 1;
+                }
+                let sandbank = board.is_sandbank_at(position);
+                reaches.push(Reach { distance: reaches.len() as i32 + 1, cost, position, sandbank });
+                if sandbank {
+                    break;
+                }
+            }
+
+            reaches
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs::read_to_string, path::PathBuf, str::FromStr};
+
+    use crate::{game::{Action, Board, CubeVec, Field, Rules, Ship, State, FREE_ACC}, util::Element};
+
+    use super::{shortest_path, Target};
+
+    /// Validates the moves of the path for the current ship with the other
+    /// ship staying in place, returning the ship after each move.
+    fn follow(state: &State, target: Target) -> Vec<Ship> {
+        let path = shortest_path(state, target).unwrap();
+        let team = state.current_team();
+        let mut current = state.clone();
+        let mut ships = Vec::new();
+        for m in &path.moves {
+            let ship = current.validate(m).unwrap_or_else(|r| panic!("Invalid move {} in path to {:?}: {:?}", m, target, r)).ship;
+            *current.ship_mut(team) = Ship { movement: ship.speed, free_acc: FREE_ACC, free_turns: 1, ..ship };
+            ships.push(ship);
+        }
+        assert_eq!(state.current_ship().coal - current.current_ship().coal, path.coal);
+        ships
+    }
+
+    /// Finds the fewest turns and the least coal among those the current ship
+    /// needs to end a move on each field, by trying all possible moves for up
+    /// to the given number of turns with the other ship staying in place.
+    fn brute_force(state: &State, max_turns: usize) -> HashMap<CubeVec, (usize, i32)> {
+        let team = state.current_team();
+        let start = state.current_ship();
+        let mut fewest = HashMap::from([(start.position, (0, 0))]);
+        let mut ships = vec![start];
+        for turns in 1..=max_turns {
+            let mut next = HashMap::new();
+            for &ship in &ships {
+                let mut current = state.clone();
+                *current.ship_mut(team) = ship;
+                for m in current.possible_moves().filter(|m| !m.iter().any(|a| matches!(a, Action::Push(_)))) {
+                    // Possible moves may turn with the coal spent on accelerating
+                    let s = current.validate(&m).unwrap().ship;
+                    if s.coal < 0 {
+                        continue;
+                    }
+                    let s = Ship { movement: s.speed, free_acc: FREE_ACC, free_turns: 1, ..s };
+                    next.entry((s.position, s.direction, s.speed, s.coal)).or_insert(s);
+                }
+            }
+            ships = next.into_values().collect();
+            for s in &ships {
+                let entry = fewest.entry(s.position).or_insert((turns, start.coal - s.coal));
+                if entry.0 == turns {
+                    entry.1 = entry.1.min(start.coal - s.coal);
+                }
+            }
+        }
+        fewest
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        for seed in 0..5 {
+            // The first three segments, with little coal so that the
+            // estimates differ by the coal left
+            let board = Board::generate_with_rules(seed, Rules::with_sandbanks());
+            let small = Board::new(board.segments()[..3].to_vec(), board.segments()[3].direction);
+            let mut state = State::new(small);
+            state.current_ship_mut().coal = 1;
+            let max_turns = 5;
+            let fewest = brute_force(&state, max_turns);
+            for (position, _) in state.board().fields() {
+                let path = shortest_path(&state, Target::Field(position)).map(|p| (p.turns(), p.coal));
+                match fewest.get(&position) {
+                    Some(&expected) => assert_eq!(path, Some(expected), "Path to {} (seed {})", position, seed),
+                    None => assert!(path.is_none_or(|(turns, _)| turns > max_turns), "Path to {} (seed {}) should take more turns", position, seed),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_target() {
+        let state = State::new(Board::generate(1));
+        let ship = state.current_ship();
+        assert_eq!(shortest_path(&state, Target::Field(ship.position)).unwrap().turns(), 0);
+
+        let target = ship.position + CubeVec::from(ship.direction) * 2;
+        let path = shortest_path(&state, Target::Field(target)).unwrap();
+        assert_eq!(path.turns(), 1);
+        assert_eq!(path.coal, 0);
+        assert_eq!(follow(&state, Target::Field(target)).last().unwrap().position, target);
+
+        // Islands can't be reached
+        let (island, _) = state.board().fields().find(|(_, f)| *f == Field::Island).unwrap();
+        assert_eq!(shortest_path(&state, Target::Field(island)), None);
+    }
+
+    #[test]
+    fn test_passenger_target() {
+        for seed in 0..4 {
+            let state = State::new(Board::generate_with_rules(seed, Rules { sandbanks: seed % 2 == 1 }));
+            let (position, direction) = state.board().fields()
+                .filter_map(|(p, f)| match f {
+                    Field::Passenger { direction, passenger: 1.. } => Some((p, direction)),
+                    _ => None,
+                })
+                .min_by_key(|(p, _)| p.distance_to(state.current_ship().position))
+                .unwrap();
+            let ships = follow(&state, Target::Passenger(position));
+            let ship = ships.last().unwrap();
+            assert_eq!(ship.position, position + direction);
+            assert!(state.board().effective_speed(*ship) < 2);
+        }
+    }
+
+    #[test]
+    fn test_goal_target() {
+        // A state from the example game once the goal is on the board
+        let path = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap().join("test-resources").join("example-game").join("48.state.xml");
+        let xml = read_to_string(path).unwrap().replacen("<state ", r#"<state class="state" "#, 1);
+        let state = &State::try_from(&Element::from_str(&xml).unwrap()).unwrap();
+        let ships = follow(state, Target::Goal);
+        let ship = ships.last().unwrap();
+        assert_eq!(state.board().get(ship.position), Some(&Field::Goal));
+        assert!(state.board().effective_speed(*ship) < 2);
+    }
+}
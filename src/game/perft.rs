@@ -0,0 +1,90 @@
+use super::{Move, State};
+
+/// Counts the leaf positions reached after applying every possible move
+/// (as generated by `State::possible_moves`) up to the given depth. A finished
+/// game has no moves. Useful for catching regressions in move generation.
+pub fn perft(state: &State, depth: usize) -> u64 {
+    perft_impl(&mut state.clone(), depth)
+}
+
+/// Counts the leaf positions like `perft`, broken down by the first move.
+pub fn perft_divide(state: &State, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 || state.is_over() {
+        return Vec::new();
+    }
+    let mut state = state.clone();
+    state.possible_moves()
+        .map(|m| {
+            let undo = state.make(&m).expect("Generated move should be valid");
+            let count = perft_impl(&mut state, depth - 1);
+            state.unmake(undo);
+            (m, count)
+        })
+        .collect()
+}
+
+fn perft_impl(state: &mut State, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if state.is_over() {
+        return 0;
+    }
+    if depth == 1 {
+        // Every move leads to a leaf, so there's no need to make them
+        return state.possible_moves().count() as u64;
+    }
+    let mut count = 0;
+    for m in state.possible_moves() {
+        let undo = state.make(&m).expect("Generated move should be valid");
+        count += perft_impl(state, depth - 1);
+        state.unmake(undo);
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::read_to_string, path::PathBuf, str::FromStr};
+
+    use crate::{game::{State, Board, Rules}, util::Element};
+
+    use super::{perft, perft_divide};
+
+    /// Checks the counts in the given corpus in test-resources.
+    fn check_corpus(name: &str) {
+        let resources_dir = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap().join("test-resources");
+        let corpus = read_to_string(resources_dir.join(name)).unwrap();
+
+        for line in corpus.lines().filter(|l| !l.starts_with('#') && !l.trim().is_empty()) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let state = match parts[0].split_once(':') {
+                Some(("seed", seed)) => State::new(Board::generate(seed.parse().unwrap())),
+                Some(("sandbanks", seed)) => State::new(Board::generate_with_rules(seed.parse().unwrap(), Rules::with_sandbanks())),
+                _ => State::try_from(&Element::from_str(&read_to_string(resources_dir.join(parts[0])).unwrap()).unwrap()).unwrap(),
+            };
+            let depth: usize = parts[1].parse().unwrap();
+            let expected: u64 = parts[2].parse().unwrap();
+            assert_eq!(perft(&state, depth), expected, "perft({}, {}) differs", parts[0], depth);
+        }
+    }
+
+    #[test]
+    fn test_reference_counts() {
+        check_corpus("perft.txt");
+    }
+
+    #[test]
+    #[ignore = "takes minutes, run with --ignored"]
+    fn test_deep_reference_counts() {
+        check_corpus("perft-deep.txt");
+    }
+
+    #[test]
+    fn test_divide_sums_up() {
+        let state = State::new(Board::generate(0));
+        assert_eq!(perft_divide(&state, 1).iter().map(|(_, c)| c).sum::<u64>(), perft(&state, 1));
+        assert!(perft_divide(&state, 1).iter().all(|&(_, c)| c == 1));
+        assert_eq!(perft(&state, 0), 1);
+    }
+}
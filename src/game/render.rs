@@ -0,0 +1,184 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+#[cfg(feature = "terminal")]
+use crossterm::style::{Color, Stylize};
+
+#[cfg(feature = "terminal")]
+use super::Ship;
+use super::{CubeVec, Field, State};
+
+/// Renders game states as text, drawing the board as an offset hex grid.
+///
+/// Every field is drawn as a two-character glyph:
+///
+/// - `~~` water, `≈≈` water with current
+/// - `##` island, `::` sandbank, `GG` goal
+/// - `P↗` passenger (lowercase once picked up) with the arrow pointing to its dock
+/// - `R→`/`B→` ship of team one/two with the arrow showing its direction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Renderer {
+    coordinates: bool,
+    #[cfg(feature = "terminal")]
+    color: bool,
+}
+
+impl Renderer {
+    /// Creates a renderer for plain output without coordinates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the cube coordinates are printed below each field.
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Sets whether the output is colored using ANSI escape codes.
+    #[cfg(feature = "terminal")]
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Renders the board and ships of the given state.
+    pub fn render(&self, state: &State) -> String {
+        let fields: HashMap<CubeVec, Field> = state.board().fields().collect();
+        let Some(xs) = Self::span(fields.keys().map(|p| Self::doubled_x(*p))) else { return String::new() };
+        let ys = Self::span(fields.keys().map(|p| p.r())).unwrap();
+
+        // Neighboring fields in a row are two doubled columns apart, with odd
+        // rows shifted by one column
+        let column_width = if self.coordinates { 5 } else { 2 };
+        let cell_width = 2 * column_width;
+        let mut output = String::new();
+
+        for r in ys {
+            let mut glyphs = String::new();
+            let mut labels = String::new();
+            let mut width = 0;
+
+            for x in xs.clone().filter(|x| (x - r).rem_euclid(2) == 0) {
+                let position = CubeVec::qr((x - r) / 2, r);
+                let Some(&field) = fields.get(&position) else { continue };
+
+                let start = (x - xs.start()) as usize * column_width;
+                glyphs += &" ".repeat(start - width);
+                glyphs += &" ".repeat((cell_width - 2) / 2);
+                glyphs += &self.glyph(state, position, field);
+                glyphs += &" ".repeat((cell_width - 2) / 2);
+
+                if self.coordinates {
+                    let label = format!("{},{},{}", position.q(), position.r(), position.s());
+                    labels += &" ".repeat(start - width);
+                    labels += &format!("{:^width$}", label, width = cell_width);
+                }
+
+                width = start + cell_width;
+            }
+
+            output += glyphs.trim_end();
+            output.push('\n');
+            if self.coordinates {
+                output += labels.trim_end();
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    /// The two-character glyph for the given field.
+    fn glyph(&self, state: &State, position: CubeVec, field: Field) -> String {
+        let ships: Vec<_> = state.ships().into_iter().filter(|s| s.position == position).collect();
+        let text = match (&ships[..], field) {
+            ([ship], _) => format!("{}{}", ship.team.letter(), ship.direction.arrow()),
+            ([one, two], _) => format!("{}{}", one.team.letter(), two.team.letter()),
+            (_, Field::Water) if state.board().does_field_have_current(position) => "≈≈".to_owned(),
+            (_, Field::Water) => "~~".to_owned(),
+            (_, Field::Island) => "##".to_owned(),
+            (_, Field::Sandbank) => "::".to_owned(),
+            (_, Field::Goal) => "GG".to_owned(),
+            (_, Field::Passenger { direction, passenger }) => format!("{}{}", if passenger > 0 { 'P' } else { 'p' }, direction.arrow()),
+        };
+
+        #[cfg(feature = "terminal")]
+        if self.color {
+            return Self::paint(text, state, position, field, &ships);
+        }
+        text
+    }
+
+    /// Colors the glyph for the given field, highlighting ships.
+    #[cfg(feature = "terminal")]
+    fn paint(text: String, state: &State, position: CubeVec, field: Field, ships: &[Ship]) -> String {
+        let color = match (ships, field) {
+            ([ship], _) => if ship.team.index() == 0 { Color::Red } else { Color::Blue },
+            ([_, _], _) => Color::Magenta,
+            (_, Field::Water) if state.board().does_field_have_current(position) => Color::Cyan,
+            (_, Field::Water) => Color::DarkBlue,
+            (_, Field::Island) => Color::DarkGreen,
+            (_, Field::Sandbank) => Color::Yellow,
+            (_, Field::Goal) => Color::Magenta,
+            (_, Field::Passenger { .. }) => Color::White,
+        };
+
+        let styled = text.with(color);
+        if ships.is_empty() { styled.to_string() } else { styled.bold().to_string() }
+    }
+
+    /// The x coordinate in the doubled-width layout.
+    fn doubled_x(position: CubeVec) -> i32 {
+        2 * position.q() + position.r()
+    }
+
+    /// The inclusive range spanned by the given values.
+    fn span(values: impl Iterator<Item=i32>) -> Option<RangeInclusive<i32>> {
+        values.fold(None, |span, v| match span {
+            Some((min, max)) => Some((v.min(min), v.max(max))),
+            None => Some((v, v)),
+        }).map(|(min, max)| min..=max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Board, State};
+
+    use super::Renderer;
+
+    #[test]
+    fn test_render() {
+        let state = State::new(Board::generate(0));
+        let output = Renderer::new().render(&state);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines.len() >= 5);
+        assert!(output.contains("R→") && output.contains("B→"));
+        assert!(output.contains("≈≈"));
+        assert_eq!(output.matches("GG").count(), 3);
+        assert!(!output.contains('\x1b'));
+
+        // Neighboring rows are shifted by half a field
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        for pair in lines.windows(2) {
+            assert_ne!(indent(pair[0]) % 4, indent(pair[1]) % 4);
+        }
+    }
+
+    #[test]
+    fn test_render_options() {
+        let state = State::new(Board::generate(0));
+        let plain = Renderer::new().render(&state);
+        let with_coordinates = Renderer::new().coordinates(true).render(&state);
+        assert_eq!(with_coordinates.lines().count(), 2 * plain.lines().count());
+        assert!(with_coordinates.contains("-1,-1,2"));
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn test_render_color() {
+        let state = State::new(Board::generate(0));
+        assert!(Renderer::new().color(true).render(&state).contains('\x1b'));
+    }
+}
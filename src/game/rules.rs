@@ -0,0 +1,27 @@
+use std::ops::RangeInclusive;
+
+use super::{MIN_SPECIAL, MAX_SPECIAL, MIN_SANDBANKS, MAX_SANDBANKS};
+
+/// Optional rule variants that affect board generation. The default matches
+/// the official tournament rules.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rules {
+    /// Whether sandbanks are placed on generated segments.
+    pub sandbanks: bool,
+}
+
+impl Rules {
+    /// Creates the default rules with sandbanks enabled.
+    pub fn with_sandbanks() -> Self {
+        Self { sandbanks: true }
+    }
+
+    /// The range of the number of sandbanks per segment.
+    pub fn sandbank_range(self) -> RangeInclusive<usize> {
+        if self.sandbanks {
+            MIN_SANDBANKS..=MAX_SANDBANKS
+        } else {
+            MIN_SPECIAL..=MAX_SPECIAL
+        }
+    }
+}
@@ -1,8 +1,10 @@
 //! Ported from https://github.com/software-challenge/backend/blob/be88340f619892fe70c4cbd45e131d5445e883c7/plugin/src/main/kotlin/sc/plugin2024/Segment.kt
 
-use std::ops::{Index, IndexMut};
+use std::{iter::repeat_n, ops::{Index, IndexMut}};
 
-use crate::util::{Error, Result, Element};
+use rand::{Rng, seq::SliceRandom};
+
+use crate::util::{Error, Result, Element, Vec2};
 
 use super::{CubeDir, CubeVec, Field};
 
@@ -51,6 +53,68 @@ impl Segment {
         self.fields.get_mut(x).and_then(|c| c.get_mut(y))
     }
 
+    /// An iterator over the global positions of all fields in the segment.
+    pub fn global_positions(&self) -> impl Iterator<Item = CubeVec> + '_ {
+        self.array_positions().map(|(x, y)| self.local_to_global(Self::local_coords(x, y)))
+    }
+
+    /// An iterator over the array indices and global positions of all fields
+    /// in the segment.
+    pub(crate) fn indexed_positions(&self) -> impl Iterator<Item = (usize, usize, CubeVec)> + '_ {
+        self.array_positions().map(|(x, y)| (x, y, self.local_to_global(Self::local_coords(x, y))))
+    }
+
+    /// Places the goal, a passenger, islands and sandbanks on the segment's
+    /// water fields, similar to the backend's segment generation.
+    pub(crate) fn populate(&mut self, is_end: bool, has_passenger: bool, islands: usize, sandbanks: usize, rng: &mut impl Rng) {
+        let width = self.fields.len();
+        let height = self.fields[0].len();
+
+        if is_end {
+            for y in 1..(height - 1) {
+                self.fields[width - 1][y] = Field::Goal;
+            }
+        }
+
+        // The passenger is never placed on the segment's edge columns and
+        // needs a water field to dock at, which is kept free of islands.
+        let mut reserved = Vec::new();
+        if has_passenger {
+            let spots: Vec<_> = self.array_positions()
+                .filter(|&(x, _)| x > 0 && x < width - 1)
+                .flat_map(|(x, y)| CubeDir::ALL.map(|d| (x, y, d)))
+                .filter(|&(x, y, d)| self.get_local(Self::local_coords(x, y) + d) == Some(&Field::Water))
+                .collect();
+            if let Some(&(x, y, d)) = spots.choose(rng) {
+                self.fields[x][y] = Field::Passenger {
+                    direction: d.rotated_by(CubeDir::Right.turn_count_to(self.direction)),
+                    passenger: 1,
+                };
+                reserved.push(Self::array_coords(Self::local_coords(x, y) + d));
+            }
+        }
+
+        let mut free: Vec<_> = self.array_positions()
+            .filter(|&(x, y)| self.fields[x][y] == Field::Water && !reserved.contains(&(x, y)))
+            .collect();
+        free.shuffle(rng);
+
+        let specials = repeat_n(Field::Island, islands).chain(repeat_n(Field::Sandbank, sandbanks));
+        for (field, (x, y)) in specials.zip(free) {
+            self.fields[x][y] = field;
+        }
+    }
+
+    /// An iterator over the array indices of all fields.
+    fn array_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.fields.iter().enumerate().flat_map(|(x, column)| (0..column.len()).map(move |y| (x, y)))
+    }
+
+    /// Fetches the local position for the given array indices.
+    fn local_coords(x: usize, y: usize) -> CubeVec {
+        CubeVec::from(Vec2::new(x as i32, y as i32))
+    }
+
     /// Fetches the array indices for the given local position.
     fn array_coords(coords: CubeVec) -> (usize, usize) {
         ((coords.array_x() + 1) as usize, (coords.r() + 2) as usize)
@@ -98,11 +162,23 @@ impl TryFrom<&Element> for Segment {
     }
 }
 
+impl From<Segment> for Element {
+    fn from(segment: Segment) -> Self {
+        Element::new("segment")
+            .attribute("direction", segment.direction)
+            .child(segment.center.to_element("center"))
+            .childs(segment.fields.into_iter().map(|column| Element::new("field-array")
+                .childs(column.into_iter().map(Element::from))
+                .build()))
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
 
-    use crate::{game::{Segment, CubeDir, CubeVec, Field}, util::assert_xml_parse};
+    use crate::{game::{Segment, CubeDir, CubeVec, Field}, util::{assert_xml_parse, assert_xml_roundtrip}};
 
     #[test]
     fn test_xml_parses() {
@@ -144,4 +220,16 @@ mod tests {
             fields: vec![vec![Field::Water; 5]; 4],
         });
     }
+
+    #[test]
+    fn test_xml_roundtrips() {
+        let mut fields = vec![vec![Field::Water; 5]; 4];
+        fields[1][2] = Field::Passenger { direction: CubeDir::UpLeft, passenger: 1 };
+        fields[3][0] = Field::Island;
+        assert_xml_roundtrip!(Segment {
+            direction: CubeDir::DownRight,
+            center: CubeVec::new(4, 4, -8),
+            fields,
+        });
+    }
 }
@@ -87,3 +87,61 @@ impl TryFrom<&Element> for Ship {
         })
     }
 }
+
+impl From<Ship> for Element {
+    fn from(ship: Ship) -> Self {
+        Element::new("ship")
+            .attribute("team", ship.team)
+            .attribute("direction", ship.direction)
+            .attribute("speed", ship.speed)
+            .attribute("coal", ship.coal)
+            .attribute("passengers", ship.passengers)
+            .attribute("freeTurns", ship.free_turns)
+            .attribute("points", ship.points)
+            .child(ship.position)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{util::{assert_xml_parse, assert_xml_format, assert_xml_roundtrip}, game::{Ship, Team, CubeVec, CubeDir}};
+
+    #[test]
+    fn test_xml_parses() {
+        assert_xml_parse!(indoc! {r#"
+            <ship team="TWO" direction="DOWN_RIGHT" speed="3" coal="4" passengers="1" freeTurns="1" points="17">
+                <position q="5" r="2" s="-7" />
+            </ship>
+        "#}, Ship {
+            team: Team::Two,
+            position: CubeVec::new(5, 2, -7),
+            direction: CubeDir::DownRight,
+            speed: 3,
+            coal: 4,
+            passengers: 1,
+            points: 17,
+            movement: 3,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_xml_formats() {
+        assert_xml_format!(
+            Ship { team: Team::One, position: CubeVec::new(-1, -1, 2), ..Default::default() },
+            indoc! {r#"
+                <ship team="ONE" direction="RIGHT" speed="1" coal="6" passengers="0" freeTurns="1" points="0">
+                    <position q="-1" r="-1" s="2" />
+                </ship>
+            "#}
+        );
+    }
+
+    #[test]
+    fn test_xml_roundtrips() {
+        assert_xml_roundtrip!(Ship { team: Team::Two, speed: 4, movement: 4, coal: 2, ..Default::default() });
+    }
+}
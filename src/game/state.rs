@@ -1,12 +1,16 @@
 //! Ported from https://github.com/software-challenge/backend/blob/be88340f619892fe70c4cbd45e131d5445e883c7/plugin/src/main/kotlin/sc/plugin2024/GameState.kt
 
-use std::{collections::VecDeque, fmt, iter::once};
+use std::{collections::{HashMap, VecDeque}, fmt, hash::{Hash, Hasher}, iter::once, ops::{Deref, DerefMut}};
 
 use arrayvec::ArrayVec;
 
-use crate::util::{Element, Error, Result, Perform, UnwrapInfallible, Vec2};
+use crate::protocol::{GameResult, Player, Score, ScoreCause, ScoreDefinition, ScoreDefinitionFragment, ScoreAggregation};
+use crate::util::{Element, Error, Result, Perform, UnwrapInfallible};
 
-use super::{Board, Move, Team, Ship, Turn, CubeVec, CubeDir, Push, Advance, AdvanceProblem, MAX_SPEED, Field, Accelerate, MIN_SPEED, Action, AccelerateProblem, ActionProblem, PushProblem, TurnProblem, MoveMistake, ROUND_LIMIT};
+use super::{zobrist, Board, Move, Team, Ship, Turn, CubeVec, CubeDir, Push, Advance, AdvanceProblem, MAX_SPEED, Field, Accelerate, MIN_SPEED, Action, AccelerateProblem, ActionProblem, PushProblem, TurnProblem, MoveMistake, MoveOutcome, MoveValidationReport, EndReason, Renderer, ROUND_LIMIT};
+
+/// The name of the score fragment holding a ship's points.
+const POINTS_FRAGMENT: &str = "∅ Punkte";
 
 /// The state of the game at a point in time.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -23,9 +27,49 @@ pub struct State {
     start_team: Team,
     /// The team to make the next move.
     current_team: Team,
+    /// The incrementally maintained Zobrist key.
+    key: u64,
 }
 
 impl State {
+    /// Creates the state at the beginning of a game on the given board.
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            turn: 0,
+            ships: [
+                Ship { team: Team::One, position: CubeVec::new(-1, -1, 2), ..Default::default() },
+                Ship { team: Team::Two, position: CubeVec::new(-2, 1, 1), ..Default::default() },
+            ],
+            last_move: None,
+            start_team: Team::One,
+            current_team: Team::One,
+            key: 0,
+        }.with_computed_key()
+    }
+
+    /// The Zobrist key of the state. It covers the ships, the current team
+    /// and the passengers left on the board, but not the turn or the last move.
+    #[inline]
+    pub fn key(&self) -> u64 { self.key }
+
+    /// Computes the Zobrist key from scratch.
+    fn compute_key(&self) -> u64 {
+        self.ships.iter().fold(zobrist::current_team_key(self.current_team), |key, &s| key ^ zobrist::ship_key(s))
+            ^ self.board.fields()
+                .filter_map(|(p, f)| match f {
+                    Field::Passenger { passenger, .. } => Some(zobrist::passenger_field_key(p, passenger)),
+                    _ => None,
+                })
+                .fold(0, |key, k| key ^ k)
+    }
+
+    /// The state with a freshly computed Zobrist key.
+    fn with_computed_key(mut self) -> Self {
+        self.key = self.compute_key();
+        self
+    }
+
     /// Fetches the board.
     #[inline]
     pub fn board(&self) -> &Board { &self.board }
@@ -54,21 +98,22 @@ impl State {
     #[inline]
     pub fn ship(&self, team: Team) -> Ship { self.ships[team.index()] }
 
-    /// The mutable ship for a team.
+    /// The mutable ship for a team. The Zobrist key is updated once the
+    /// returned guard is dropped.
     #[inline]
-    pub fn ship_mut(&mut self, team: Team) -> &mut Ship { &mut self.ships[team.index()] }
+    pub fn ship_mut(&mut self, team: Team) -> ShipMut<'_> { ShipMut::new(&mut self.ships[team.index()], &mut self.key) }
 
     /// The current team's ship.
     pub fn current_ship(&self) -> Ship { self.ship(self.current_team()) }
 
     /// The current team's ship, mutably.
-    pub fn current_ship_mut(&mut self) -> &mut Ship { self.ship_mut(self.current_team()) }
+    pub fn current_ship_mut(&mut self) -> ShipMut<'_> { self.ship_mut(self.current_team()) }
 
     /// The opponent team's ship.
     pub fn other_ship(&self) -> Ship { self.ship(self.other_team()) }
 
     /// The opponent team's ship, mutably.
-    pub fn other_ship_mut(&mut self) -> &mut Ship { self.ship_mut(self.other_team()) }
+    pub fn other_ship_mut(&mut self) -> ShipMut<'_> { self.ship_mut(self.other_team()) }
 
     /// The ships.
     pub fn ships(&self) -> [Ship; Team::COUNT] { self.ships }
@@ -134,7 +179,7 @@ impl State {
         if self.must_push() || self.board.is_sandbank_at(ship.position) {
             return Vec::new();
         }
-        let max_turn_count = (max_coal + ship.free_turns).min(3) as i32;
+        let max_turn_count = (max_coal + ship.free_turns).min(3);
         (1..=max_turn_count)
             .flat_map(|i| [i, -i])
             .map(|turns| Turn::new(ship.direction.rotated_by(turns)))
@@ -154,14 +199,14 @@ impl State {
     /// Fetches the possible accelerations for the current player with the given
     /// amount of coal.
     fn possible_accelerations_with(&self, max_coal: i32) -> Vec<Accelerate> {
-        if self.must_push() {
+        let ship = self.current_ship();
+        if self.must_push() || self.board.is_sandbank_at(ship.position) {
             return Vec::new();
         }
 
-        let ship = self.current_ship();
-        return (1..=(max_coal + ship.free_acc))
+        (1..=(max_coal + ship.free_acc))
             .flat_map(|i| [i, -i])
-            .filter(|&i| if i > 0 { MAX_SPEED >= ship.speed + i } else { MIN_SPEED <= ship.speed - i })
+            .filter(|&i| if i > 0 { MAX_SPEED >= ship.speed + i } else { MIN_SPEED <= ship.speed + i })
             .map(Accelerate::new)
             .collect()
     }
@@ -177,7 +222,7 @@ impl State {
         if self.board.is_sandbank_at(ship.position) {
             Some([-1, 1].into_iter()
                 .map(Advance::new)
-                .filter(|a| self.advance_limit_with(ship.position, ship.direction.opposite_if(a.distance < 0), 1).distance() > 1)
+                .filter(|a| self.advance_limit_with(ship.position, ship.direction.opposite_if(a.distance < 0), 1).distance() > 0)
                 .collect())
         } else {
             None
@@ -194,7 +239,7 @@ impl State {
         let mut current_pos = start;
         let mut total_cost = 0;
         let mut has_current = false;
-        let max_movement = max_movement.max(0).min(MAX_SPEED);
+        let max_movement = max_movement.clamp(0, MAX_SPEED);
         let mut costs = Vec::new();
 
         macro_rules! result {
@@ -222,6 +267,10 @@ impl State {
             }
 
             if self.ships.iter().any(|s| s.position == current_pos) {
+                // Pushing off a sandbank isn't allowed, so a ship there blocks the field
+                if let Field::Sandbank = current_field {
+                    return result!(AdvanceProblem::FieldIsBlocked);
+                }
                 if total_cost < max_movement {
                     costs.push(total_cost);
                     return result!(AdvanceProblem::ShipAlreadyInTarget);
@@ -230,6 +279,8 @@ impl State {
             }
 
             if let Field::Sandbank = current_field {
+                // Ships may run onto a sandbank, but have to stop there
+                costs.push(total_cost);
                 return result!(AdvanceProblem::MoveEndOnSandbank);
             }
 
@@ -241,20 +292,97 @@ impl State {
 
     /// Whether the game is over.
     pub fn is_over(&self) -> bool {
-        // Case 1: Ship with two passengers reaches a goal field with speed 1
-        (self.turn % 2 == 0 && self.ships.into_iter().any(|s| self.is_winner(s)))
-        // Case 2: Player performs an invalid move => this is handled via `MoveMistake` errors
-        // Case 3: A ship is more than 3 segments behind at the end of a round
-        || self.board.segment_distance(self.ships[0].position, self.ships[1].position) > 3
-        // Case 4: The round limit of 30 rounds has been reached
-        || self.turn / 2 >= ROUND_LIMIT
-        // Case 5: Neither player can move
-        || (self.last_move.is_none() && !self.can_move())
-        // Otherwise the game continues...
+        self.end_reason().is_some()
+    }
+
+    /// Why the game is over, or `None` if it continues. Invalid moves also
+    /// end the game, but these are reported as `MoveMistake` errors instead.
+    pub fn end_reason(&self) -> Option<EndReason> {
+        self.end_reason_with(|| self.can_move())
+    }
+
+    /// Why the game is over, asking the given function whether the current
+    /// team can move only if it matters.
+    fn end_reason_with(&self, can_move: impl FnOnce() -> bool) -> Option<EndReason> {
+        let [one, two] = self.ships;
+        if self.turn.is_multiple_of(2) && self.ships.into_iter().any(|s| self.is_winner(s)) {
+            Some(EndReason::GoalReached)
+        } else if self.board.segment_distance(one.position, two.position) > 3 {
+            let behind = if self.board.segment_index_at(one.position) < self.board.segment_index_at(two.position) { one.team } else { two.team };
+            Some(EndReason::TooFarBehind(behind))
+        } else if self.turn / 2 >= ROUND_LIMIT {
+            Some(EndReason::RoundLimit)
+        } else if self.last_move.is_none() && !can_move() {
+            Some(EndReason::NoMoves)
+        } else {
+            None
+        }
+    }
+
+    /// The result of the finished game, scored the way the server does.
+    pub fn result(&self) -> GameResult {
+        self.result_with(None)
+    }
+
+    /// The points the given team's ship had at the end of a game, as scored
+    /// in the result. `None` if the result doesn't score points for the team.
+    pub fn result_points(result: &GameResult, team: Team) -> Option<i32> {
+        let part = result.definition().fragments().iter().position(|f| f.name() == POINTS_FRAGMENT)?;
+        result.scores().get(&Player::new(None, team))?.parts().get(part).copied()
+    }
+
+    /// The result of a game that ended because the given team performed an
+    /// invalid move. The other team wins.
+    pub fn violation_result(&self, team: Team, mistake: MoveMistake) -> GameResult {
+        self.result_with(Some((team, ScoreCause::RuleViolation, format!("{:?}", mistake))))
+    }
+
+    /// The result of a game that ended because the given team took too long
+    /// to move. The other team wins.
+    pub fn timeout_result(&self, team: Team) -> GameResult {
+        self.result_with(Some((team, ScoreCause::SoftTimeout, "Move took too long".to_owned())))
+    }
+
+    /// Computes the result, optionally for a game forfeited by a team for the
+    /// given cause and reason.
+    fn result_with(&self, forfeit: Option<(Team, ScoreCause, String)>) -> GameResult {
+        let winner = match forfeit {
+            Some((team, _, _)) => Some(team.opponent()),
+            None => {
+                let [one, two] = self.ships;
+                match (self.end_reason(), self.is_winner(one), self.is_winner(two)) {
+                    // The leading ship wins regardless of the points
+                    (Some(EndReason::TooFarBehind(behind)), _, _) => Some(behind.opponent()),
+                    (_, true, false) => Some(Team::One),
+                    (_, false, true) => Some(Team::Two),
+                    _ if one.points != two.points => Some(if one.points > two.points { Team::One } else { Team::Two }),
+                    _ => None,
+                }
+            },
+        };
+
+        let definition = ScoreDefinition::new([
+            ScoreDefinitionFragment::new("Siegpunkte", ScoreAggregation::Sum, true),
+            ScoreDefinitionFragment::new(POINTS_FRAGMENT, ScoreAggregation::Average, true),
+        ]);
+        let scores = Team::ALL.map(|team| {
+            let (cause, reason) = match &forfeit {
+                Some((t, cause, reason)) if *t == team => (*cause, reason.as_str()),
+                _ => (ScoreCause::Regular, ""),
+            };
+            let win_points = match winner {
+                Some(w) if w == team => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+            (Player::new(None, team), Score::new(cause, reason, [win_points, self.ship(team).points]))
+        });
+
+        GameResult::new(definition, scores, winner.map(|w| Player::new(None, w)))
     }
 
     /// Whether the given ship is a winner.
-    fn is_winner(&self, ship: Ship) -> bool {
+    pub fn is_winner(&self, ship: Ship) -> bool {
         ship.passengers > 1
         && self.board.effective_speed(ship) < 2
         && matches!(self.board.get(ship.position), Some(Field::Goal))
@@ -274,6 +402,48 @@ impl State {
         MoveIterator::new(self.clone())
     }
 
+    /// Fetches one move per distinct position reachable by the possible moves.
+    /// Of the moves leading to the same position, regardless of the coal left
+    /// afterwards, the one using the least coal (and then the fewest actions)
+    /// is picked.
+    pub fn canonical_moves(&self) -> Vec<CanonicalMove> {
+        let mut state = self.clone();
+        let mut moves: Vec<CanonicalMove> = Vec::new();
+        let mut indices: HashMap<_, usize> = HashMap::new();
+        let coal = self.current_ship().coal;
+
+        for m in self.possible_moves() {
+            let Ok(undo) = state.make(&m) else { continue };
+            let key = state.key();
+            let position = state.canonical_key();
+            let cost = (coal - state.ship(self.current_team()).coal, m.actions.len());
+            state.unmake(undo);
+
+            match indices.get(&position) {
+                Some(&i) => {
+                    let canonical = &mut moves[i];
+                    canonical.folded += 1;
+                    if cost < canonical.cost() {
+                        canonical.key = key;
+                        canonical.coal_used = cost.0;
+                        canonical.m = m;
+                    }
+                },
+                None => {
+                    indices.insert(position, moves.len());
+                    moves.push(CanonicalMove { m, key, coal_used: cost.0, folded: 1 });
+                },
+            }
+        }
+
+        moves
+    }
+
+    /// Identifies the position for folding equivalent moves.
+    fn canonical_key(&self) -> CanonicalKey {
+        (self.ships.map(|s| (s.position, s.direction, s.speed, s.passengers, s.points)), self.current_team)
+    }
+
     // TODO: Add sensible_moves that computes the simple moves and falls back to the iterator otherwise.
 
     /// Fetches the simple moves for the current ship.
@@ -284,6 +454,10 @@ impl State {
     /// Fetches the simple moves (accelerate + turn + move) using at most the
     /// given amount of coal.
     pub fn simple_moves_with(&self, max_coal: i32) -> Vec<Move> {
+        if let Some(advances) = self.sandbank_advances_for(self.current_ship()) {
+            return advances.into_iter().map(|adv| Move::from(Action::Advance(adv))).collect();
+        }
+
         once(None)
             .chain(self.possible_turns_with(max_coal.min(1)).into_iter().map(Some))
             .flat_map(|turn| {
@@ -339,25 +513,240 @@ impl State {
         self.possible_moves().next().is_some()
     }
 
+    /// Whether the player can move, searching for a move in place rather
+    /// than on a clone of the state.
+    fn has_move(&mut self) -> bool {
+        let (ships, key) = (self.ships, self.key);
+        let team = self.current_team;
+        let mut queue = VecDeque::from([(ships, Move::new())]);
+        let mut found = false;
+        while let Some((partial_ships, partial_move)) = queue.pop_front() {
+            if partial_ships[team.index()].movement == 0 {
+                found = true;
+                break;
+            }
+            self.set_ships(partial_ships);
+            self.extend_move(&partial_move, |ships, m| queue.push_back((ships, m)));
+        }
+        self.ships = ships;
+        self.key = key;
+        found
+    }
+
+    /// Passes each continuation of the given partial move to the callback,
+    /// along with the ships after it. The actions are performed in place and
+    /// the ships are restored afterwards.
+    fn extend_move(&mut self, current_move: &Move, mut push: impl FnMut([Ship; Team::COUNT], Move)) {
+        let ships = self.ships;
+
+        if !matches!(current_move.last(), Some(Action::Advance(_))) {
+            for adv in self.possible_advances() {
+                self.perform(adv).unwrap();
+                let child_move = current_move.child(Action::Advance(adv)).unwrap();
+                let pushes = self.possible_pushes();
+                if pushes.is_empty() {
+                    push(self.ships, child_move);
+                } else {
+                    let advanced = self.ships;
+                    for p in pushes {
+                        self.perform(p).unwrap();
+                        push(self.ships, child_move.child(Action::Push(p)).unwrap());
+                        self.set_ships(advanced);
+                    }
+                }
+                self.set_ships(ships);
+            }
+        }
+
+        if !matches!(current_move.last(), Some(Action::Turn(_))) {
+            for turn in self.possible_turns() {
+                self.perform(turn).unwrap();
+                push(self.ships, current_move.child(Action::Turn(turn)).unwrap());
+                self.set_ships(ships);
+            }
+        }
+
+        if current_move.is_empty() {
+            for acc in self.possible_accelerations() {
+                self.current_ship_mut().accelerate(acc.acc);
+                push(self.ships, Move::from(Action::Accelerate(acc)));
+                self.set_ships(ships);
+            }
+        }
+    }
+
+    /// Replaces the ships, updating the Zobrist key.
+    fn set_ships(&mut self, ships: [Ship; Team::COUNT]) {
+        let ships_key = |ships: &[Ship]| ships.iter().fold(0, |key, &s| key ^ zobrist::ship_key(s));
+        self.key ^= ships_key(&self.ships) ^ ships_key(&ships);
+        self.ships = ships;
+    }
+
     /// Increments the turn and updates the current team.
     fn advance_turn(&mut self) {
-        let ship = self.current_ship_mut();
-        ship.free_acc = 1;
-        ship.free_turns = 1;
-        ship.movement = ship.speed;
+        {
+            let mut ship = self.current_ship_mut();
+            ship.free_acc = 1;
+            ship.free_turns = 1;
+            ship.movement = ship.speed;
+        }
 
         self.turn += 1;
-        self.current_team = if self.turn % 2 == 0 {
+        let next_team = if self.turn.is_multiple_of(2) {
             self.determine_ahead_team()
         } else {
             self.current_team.opponent()
         };
+        self.key ^= zobrist::current_team_key(self.current_team) ^ zobrist::current_team_key(next_team);
+        self.current_team = next_team;
 
-        if !self.can_move() && !self.is_over() {
+        // The end reason only asks whether we can move after a skipped turn,
+        // which we already know we can't
+        if !self.has_move() && self.end_reason_with(|| false).is_none() {
             self.last_move = None;
             self.advance_turn();
         }
     }
+
+    /// Checks whether the given move is valid without modifying the state.
+    /// On failure, the report describes the failing action and the ship at
+    /// that point.
+    pub fn validate(&self, m: &Move) -> Result<MoveOutcome, MoveValidationReport> {
+        let team = self.current_team();
+        let ship = self.current_ship();
+        let mut state = self.clone();
+        state.perform_actions(m)?;
+        state.finish_move(m.clone());
+        let moved_ship = state.ship(team);
+        Ok(MoveOutcome {
+            ship: moved_ship,
+            other_ship: state.ship(team.opponent()),
+            coal_used: ship.coal - moved_ship.coal,
+            passengers_picked_up: moved_ship.passengers - ship.passengers,
+            next_team: state.current_team(),
+        })
+    }
+
+    /// Performs the given move, returning the positions of the passenger
+    /// fields that passengers were picked up from.
+    fn perform_move(&mut self, m: Move) -> Result<ArrayVec<CubeVec, {Team::COUNT}>, MoveMistake> {
+        self.perform_actions(&m).map_err(|r| r.mistake)?;
+        Ok(self.finish_move(m))
+    }
+
+    /// Performs the actions of the given move and checks that the movement
+    /// points were used up.
+    fn perform_actions(&mut self, m: &Move) -> Result<(), MoveValidationReport> {
+        let report = |ship: Ship, action: Option<(usize, Action)>, mistake: MoveMistake| MoveValidationReport {
+            action_index: action.map(|(i, _)| i),
+            action: action.map(|(_, a)| a),
+            mistake,
+            ship,
+        };
+
+        if m.is_empty() {
+            return Err(report(self.current_ship(), None, MoveMistake::NoActions));
+        }
+
+        for (i, (index, action)) in m.coalesced_with_indices().into_iter().enumerate() {
+            let ship = self.current_ship();
+            if i != 0 && self.board.is_sandbank_at(ship.position) {
+                return Err(report(ship, Some((index, action)), MoveMistake::SandbankEnd));
+            }
+            if self.must_push() && !matches!(action, Action::Push(_)) {
+                return Err(report(ship, Some((index, action)), MoveMistake::PushActionRequired));
+            }
+            if i != 0 && matches!(action, Action::Accelerate(_)) {
+                return Err(report(ship, Some((index, action)), MoveMistake::FirstActionAccelerate));
+            }
+            self.perform(action).map_err(|p| report(ship, Some((index, action)), p.into()))?;
+        }
+
+        let ship = self.current_ship();
+        match ship.movement {
+            p if p > 0 => Err(report(ship, None, MoveMistake::MovementPointsLeft(p))),
+            p if p < 0 => Err(report(ship, None, MoveMistake::MovementPointsMissing(-p))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Picks up passengers, updates the points and advances the turn after
+    /// the actions of the given move have been performed. Returns the
+    /// positions of the passenger fields that passengers were picked up from.
+    fn finish_move(&mut self, m: Move) -> ArrayVec<CubeVec, {Team::COUNT}> {
+        // Note: We can't use `current_ship_mut()` since we need to prove to the
+        // borrow checker that these mutable borrows don't overlap when mutating
+        // `board`. Additionally, we need a mutable reference to each of the
+        // ships, therefore we need to juggle a bit with `split_at_mut`...
+        // Perhaps we should wrap the `[Ship; 2]` and then add a convenience
+        // method for mutably borrowing 'our' and the other ship (given a team).
+        // Maybe we'd just want to store the ships directly as fields (rather
+        // than as an array)? The downside would be that deserialization would
+        // be a bit less convenient (and require some manual validation)
+        let ships_key = self.ships.iter().fold(0, |key, &s| key ^ zobrist::ship_key(s));
+        let (front, back) = self.ships.split_at_mut(1);
+        let (ship, other_ship) = match self.current_team {
+            Team::One => (&mut front[0], &mut back[0]),
+            Team::Two => (&mut back[0], &mut front[0]),
+        };
+
+        let mut picked_up = ArrayVec::<CubeVec, {Team::COUNT}>::new();
+        picked_up.extend(self.board.pick_up_passenger(ship));
+        ship.points = self.board.ship_points(*ship).expect("Could not calculate ship points");
+        if m.actions.iter().any(|a| matches!(a, Action::Push(_))) {
+            if other_ship.speed == 1 {
+                picked_up.extend(self.board.pick_up_passenger(other_ship));
+            }
+            other_ship.points = self.board.ship_points(*other_ship).expect("Could not calculate other ship's points");
+        }
+
+        self.key ^= ships_key ^ self.ships.iter().fold(0, |key, &s| key ^ zobrist::ship_key(s));
+        for &position in &picked_up {
+            if let Field::Passenger { passenger, .. } = self.board[position] {
+                self.key ^= zobrist::passenger_field_key(position, passenger + 1) ^ zobrist::passenger_field_key(position, passenger);
+            }
+        }
+
+        self.last_move = Some(m);
+        self.advance_turn();
+
+        picked_up
+    }
+
+    /// Performs the given move in place and returns the information needed to
+    /// take it back with `unmake`. Unlike `child`, this doesn't clone the
+    /// board, not even to check whether the next team can move. If the move
+    /// is invalid, the state is left unchanged.
+    pub fn make(&mut self, m: &Move) -> Result<Undo, MoveMistake> {
+        let ships = self.ships;
+        let key = self.key;
+        let turn = self.turn;
+        let current_team = self.current_team;
+        let last_move = self.last_move.take();
+
+        match self.perform_move(m.clone()) {
+            Ok(picked_up) => Ok(Undo { ships, turn, current_team, last_move, key, picked_up }),
+            Err(mistake) => {
+                self.ships = ships;
+                self.key = key;
+                self.last_move = last_move;
+                Err(mistake)
+            },
+        }
+    }
+
+    /// Takes back the move that produced the given undo information. Moves
+    /// have to be taken back in the reverse order they were made in.
+    pub fn unmake(&mut self, undo: Undo) {
+        for position in undo.picked_up {
+            self.board.return_passenger(position);
+        }
+        self.ships = undo.ships;
+        self.turn = undo.turn;
+        self.current_team = undo.current_team;
+        self.last_move = undo.last_move;
+        self.key = undo.key;
+    }
 }
 
 impl Perform<Accelerate> for State {
@@ -371,7 +760,7 @@ impl Perform<Accelerate> for State {
         {
             let ship = self.current_ship();
 
-            match ship.speed {
+            match ship.speed + acc.acc {
                 // TODO: Can we match against the MAX_SPEED/MIN_SPEED constants?
                 7.. => return Err(AccelerateProblem::AboveMaxSpeed),
                 ..=0 => return Err(AccelerateProblem::BelowMinSpeed),
@@ -379,7 +768,7 @@ impl Perform<Accelerate> for State {
             }
 
             if self.board.is_sandbank_at(ship.position) {
-                return Err(AccelerateProblem::InsufficientCoal);
+                return Err(AccelerateProblem::OnSandbank);
             }
         }
 
@@ -415,9 +804,17 @@ impl Perform<Advance> for State {
             return Err(limit.problem);
         }
 
-        let ship = self.current_ship_mut();
-        ship.position += CubeVec::from(ship.direction) * adv.distance;
-        ship.movement -= limit.cost_until(adv.distance);
+        let on_sandbank = self.board.is_sandbank_at(self.current_ship().position + CubeVec::from(self.current_ship().direction) * adv.distance);
+        let mut ship = self.current_ship_mut();
+        let direction = ship.direction;
+        ship.position += CubeVec::from(direction) * adv.distance;
+        ship.movement -= limit.cost_until(adv.distance.abs());
+
+        // Running onto a sandbank slows the ship down and ends its movement
+        if on_sandbank {
+            ship.speed = 1;
+            ship.movement = 0;
+        }
 
         Ok(())
     }
@@ -430,13 +827,17 @@ impl Perform<Push> for State {
         let team = self.current_team();
         let nudged_team = self.other_team();
 
+        if self.board.is_sandbank_at(self.ship(team).position) {
+            return Err(PushProblem::SandbankPush);
+        }
+
         self.ship_mut(team).movement -= 1;
 
         let push_from = self.ship(team).position;
         let push_to = push_from + push.direction;
         let is_sandbank = self.board.is_sandbank_at(push_to);
 
-        let nudged_ship = self.ship_mut(nudged_team);
+        let mut nudged_ship = self.ship_mut(nudged_team);
 
         if is_sandbank {
             nudged_ship.speed = 1;
@@ -453,9 +854,13 @@ impl Perform<Turn> for State {
     type Error = TurnProblem;
 
     fn perform(&mut self, turn: Turn) -> Result<(), TurnProblem> {
+        if self.board.is_sandbank_at(self.current_ship().position) {
+            return Err(TurnProblem::RotationOnSandbankNotAllowed);
+        }
+
         let turn_count = self.current_ship().direction.turn_count_to(turn.direction);
         let abs_turn_count = turn_count.abs();
-        let free_turns = self.current_ship().free_turns as i32;
+        let free_turns = self.current_ship().free_turns;
         let used_coal = abs_turn_count - free_turns;
 
         self.current_ship_mut().free_turns = (free_turns - abs_turn_count).max(0);
@@ -473,12 +878,13 @@ impl Perform<Action> for State {
 
     /// Performs the given action.
     fn perform(&mut self, action: Action) -> Result<(), ActionProblem> {
-        Ok(match action {
+        match action {
             Action::Accelerate(acc) => self.perform(acc)?,
             Action::Advance(adv) => self.perform(adv)?,
             Action::Push(push) => self.perform(push)?,
             Action::Turn(turn) => self.perform(turn)?,
-        })
+        }
+        Ok(())
     }
 }
 
@@ -486,57 +892,60 @@ impl Perform<Move> for State {
     type Error = MoveMistake;
 
     fn perform(&mut self, m: Move) -> Result<(), MoveMistake> {
-        if m.is_empty() {
-            return Err(MoveMistake::NoActions);
-        }
+        self.perform_move(m)?;
+        Ok(())
+    }
+}
 
-        for (i, action) in m.coalesced().into_iter().enumerate() {
-            if i != 0 && self.board.is_sandbank_at(self.current_ship().position) {
-                return Err(MoveMistake::SandbankEnd);
-            }
-            if self.must_push() && !matches!(action, Action::Push(_)) {
-                return Err(MoveMistake::PushActionRequired);
-            }
-            if i != 0 && matches!(action, Action::Accelerate(_)) {
-                return Err(MoveMistake::FirstActionAccelerate);
-            }
-            self.perform(action)?;
-        }
+/// The information needed to take back a move made with `State::make`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+    ships: [Ship; Team::COUNT],
+    turn: usize,
+    current_team: Team,
+    last_move: Option<Move>,
+    key: u64,
+    picked_up: ArrayVec<CubeVec, {Team::COUNT}>,
+}
 
-        match self.current_ship().movement {
-            p if p > 0 => return Err(MoveMistake::MovementPointsLeft(p)),
-            p if p < 0 => return Err(MoveMistake::MovementPointsMissing(-p)),
-            _ => {},
-        }
+impl Hash for State {
+    fn hash<H>(&self, state: &mut H) where H: Hasher {
+        self.key.hash(state);
+    }
+}
 
-        // Note: We can't use `current_ship_mut()` since we need to prove to the
-        // borrow checker that these mutable borrows don't overlap when mutating
-        // `board`. Additionally, we need a mutable reference to each of the
-        // ships, therefore we need to juggle a bit with `split_at_mut`...
-        // Perhaps we should wrap the `[Ship; 2]` and then add a convenience
-        // method for mutably borrowing 'our' and the other ship (given a team).
-        // Maybe we'd just want to store the ships directly as fields (rather
-        // than as an array)? The downside would be that deserialization would
-        // be a bit less convenient (and require some manual validation)
-        let (front, back) = self.ships.split_at_mut(1);
-        let (ship, other_ship) = match self.current_team {
-            Team::One => (&mut front[0], &mut back[0]),
-            Team::Two => (&mut back[0], &mut front[0]),
-        };
+/// A mutable borrow of a ship within a state that updates the state's
+/// Zobrist key when dropped.
+pub struct ShipMut<'a> {
+    ship: &'a mut Ship,
+    key: &'a mut u64,
+    ship_key: u64,
+}
 
-        self.board.pick_up_passenger(ship);
-        ship.points = self.board.ship_points(*ship).expect("Could not calculate ship points");
-        if m.actions.iter().any(|a| matches!(a, Action::Push(_))) {
-            if other_ship.speed == 1 {
-                self.board.pick_up_passenger(other_ship);
-            }
-            other_ship.points = self.board.ship_points(*other_ship).expect("Could not calculate other ship's points");
-        }
+impl<'a> ShipMut<'a> {
+    fn new(ship: &'a mut Ship, key: &'a mut u64) -> Self {
+        let ship_key = zobrist::ship_key(*ship);
+        Self { ship, key, ship_key }
+    }
+}
 
-        self.last_move = Some(m);
-        self.advance_turn();
+impl Deref for ShipMut<'_> {
+    type Target = Ship;
 
-        Ok(())
+    fn deref(&self) -> &Ship {
+        self.ship
+    }
+}
+
+impl DerefMut for ShipMut<'_> {
+    fn deref_mut(&mut self) -> &mut Ship {
+        self.ship
+    }
+}
+
+impl Drop for ShipMut<'_> {
+    fn drop(&mut self) {
+        *self.key ^= self.ship_key ^ zobrist::ship_key(*self.ship);
     }
 }
 
@@ -556,73 +965,60 @@ impl AdvanceLimit {
     }
 
     pub fn advances(&self) -> impl Iterator<Item = Advance> {
-        (1..=self.distance()).rev().map(|d| Advance::new(d as i32))
+        (1..=self.distance()).rev().map(Advance::new)
+    }
+}
+
+/// The ships' position, direction, speed, passengers and points and the
+/// team to move, but not the coal spent on getting there.
+type CanonicalKey = ([(CubeVec, CubeDir, i32, usize, i32); Team::COUNT], Team);
+
+/// A move standing in for all possible moves that lead to the same position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalMove {
+    /// The cheapest of the equivalent moves.
+    pub m: Move,
+    /// The Zobrist key of the resulting position.
+    pub key: u64,
+    /// The amount of coal the move uses.
+    pub coal_used: i32,
+    /// The number of raw action sequences folded into this move.
+    pub folded: usize,
+}
+
+impl CanonicalMove {
+    /// The cost by which equivalent moves are compared.
+    fn cost(&self) -> (i32, usize) {
+        (self.coal_used, self.m.actions.len())
     }
 }
 
+/// Iterates the possible moves breadth-first. The partial moves are queued
+/// with the ships after them and performed on a single copy of the state.
 pub struct MoveIterator {
-    queue: VecDeque<(State, Move)>,
+    state: State,
+    queue: VecDeque<([Ship; Team::COUNT], Move)>,
 }
 
 impl MoveIterator {
     fn new(state: State) -> Self {
         let mut queue = VecDeque::new();
-        queue.push_back((state, Move::new()));
-        MoveIterator { queue }
+        queue.push_back((state.ships, Move::new()));
+        MoveIterator { state, queue }
     }
 
     fn process(&mut self) -> Option<Move> {
-        if let Some((state, current_move)) = self.queue.pop_front() {
-            if !matches!(current_move.last(), Some(Action::Advance(_))) {
-                for adv in state.possible_advances() {
-                    let child_state = state.child(adv).unwrap();
-                    let child_move = current_move.child(Action::Advance(adv)).unwrap();
-                    let pushes = child_state.possible_pushes();
-                    if pushes.is_empty() {
-                        self.queue.push_back((child_state, child_move));
-                    } else {
-                        for push in pushes {
-                            self.queue.push_back((
-                                state.child(push).unwrap(),
-                                child_move.child(Action::Push(push)).unwrap(),
-                            ));
-                        }
-                    }
-                }
-            }
-
-            if !matches!(current_move.last(), Some(Action::Turn(_))) {
-                for turn in state.possible_turns() {
-                    self.queue.push_back((
-                        state.child(turn).unwrap(),
-                        current_move.child(Action::Turn(turn)).unwrap(),
-                    ));
-                }
-            }
-
-            if current_move.is_empty() {
-                for acc in state.possible_accelerations() {
-                    let mut new_state = state.clone();
-                    new_state.ships = state.ships.map(|s| {
-                        if s.team == state.current_team() {
-                            s.accelerated(acc.acc)
-                        } else {
-                            s
-                        }
-                    });
-                    self.queue.push_back((new_state, Move::from(Action::Accelerate(acc))))
-                }
-            }
-
-            Some(current_move)
-        } else {
-            None
-        }
+        let (ships, current_move) = self.queue.pop_front()?;
+        let Self { state, queue } = self;
+        state.set_ships(ships);
+        state.extend_move(&current_move, |ships, m| queue.push_back((ships, m)));
+        Some(current_move)
     }
 
     fn find_next(&mut self) {
-        while let Some((state, _)) = self.queue.front() {
-            if state.current_ship().movement == 0 {
+        let team = self.state.current_team();
+        while let Some((ships, _)) = self.queue.front() {
+            if ships[team.index()].movement == 0 {
                 break;
             }
             self.process();
@@ -641,40 +1037,7 @@ impl Iterator for MoveIterator {
 
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: Use better coordinate conversion
-        let (xs, ys) = self.board.bounds();
-        for y in ys {
-            for x in xs.clone() {
-                let cube_pos = CubeVec::from(Vec2::new(x, y));
-                let c = self.ships
-                    .into_iter()
-                    .enumerate()
-                    .find(|(_, s)| s.position == cube_pos)
-                    .and_then(|(i, _)| char::from_digit(i as u32, 10))
-                    .unwrap_or_else(|| match self.board.get(cube_pos) {
-                        Some(Field::Water) => '~',
-                        Some(Field::Island) => '#',
-                        Some(Field::Sandbank) => '_',
-                        Some(Field::Passenger { direction, passenger }) => if *passenger > 0 {
-                            match direction {
-                                CubeDir::Right => '>',
-                                CubeDir::DownRight => '\\',
-                                CubeDir::DownLeft => '/',
-                                CubeDir::Left => '<',
-                                CubeDir::UpLeft => '`',
-                                CubeDir::UpRight => '´',
-                            }
-                        } else {
-                            '0'
-                        },
-                        Some(Field::Goal) => 'G',
-                        None => '.',
-                    });
-                write!(f, "{}", c)?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        write!(f, "{}", Renderer::new().render(self))
     }
 }
 
@@ -683,6 +1046,7 @@ impl TryFrom<&Element> for State {
 
     fn try_from(elem: &Element) -> Result<Self> {
         Ok(State {
+            key: 0,
             board: elem.child_by_name("board")?.try_into()?,
             turn: elem.attribute("turn")?.parse()?,
             // TODO: This currently relies on that ships are ordered by team (first ONE, then TWO)
@@ -695,17 +1059,67 @@ impl TryFrom<&Element> for State {
             last_move: elem.child_by_name("lastMove").ok().and_then(|m| m.try_into().ok()),
             start_team: elem.attribute("startTeam")?.parse()?,
             current_team: elem.attribute("currentTeam")?.parse()?,
-        })
+        }.with_computed_key())
+    }
+}
+
+impl From<State> for Element {
+    fn from(state: State) -> Self {
+        Element::new("state")
+            .attribute("class", "state")
+            .attribute("startTeam", state.start_team)
+            .attribute("turn", state.turn)
+            .attribute("currentTeam", state.current_team)
+            .child(state.board)
+            .childs(state.ships.into_iter().map(Element::from))
+            .option_child(state.last_move.map(|m| Element::new("lastMove")
+                .child(Element::new("actions")
+                    .childs(m.actions.into_iter().map(Element::from)))
+                .build()))
+            .build()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{fs::{DirEntry, read_to_string, create_dir_all}, str::FromStr, path::PathBuf};
+    use std::{collections::HashMap, fs::{DirEntry, read_to_string, create_dir_all}, str::FromStr, path::PathBuf};
 
     use indoc::indoc;
 
-    use crate::{game::{State, Ship, CubeVec, Team, CubeDir, Board, Segment, Field, FREE_ACC, Move}, util::{assert_xml_parse, Element, Perform}};
+    use crate::{game::{State, Ship, CubeVec, Team, CubeDir, Board, Segment, Field, FREE_ACC, Move, MoveMistake, Action, ActionProblem, AdvanceProblem, AccelerateProblem, TurnProblem, PushProblem, EndReason, ROUND_LIMIT, BOARD_CLONES}, protocol::{GameResult, Player, Score, ScoreCause}, util::{assert_xml_parse, assert_xml_roundtrip, Element, Perform}};
+
+    /// The files of the example game, sorted by name.
+    fn example_game_entries() -> Vec<DirEntry> {
+        let manifest_dir = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let game_dir = manifest_dir.join("test-resources").join("example-game");
+
+        create_dir_all(&game_dir).unwrap();
+        let mut entries: Vec<DirEntry> = game_dir.read_dir().unwrap()
+            .map(|e| e.unwrap())
+            .collect();
+
+        entries.sort_by_key(|e| e.file_name());
+        entries
+    }
+
+    /// The state elements of the example game, in the form the server sends
+    /// them within a memento.
+    fn example_game_state_elements() -> Vec<Element> {
+        example_game_entries().into_iter()
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".state.xml"))
+            .map(|e| {
+                let xml = read_to_string(e.path()).unwrap().replacen("<state ", r#"<state class="state" "#, 1);
+                Element::from_str(&xml).unwrap()
+            })
+            .collect()
+    }
+
+    /// The states of the example game.
+    fn example_game_states() -> Vec<State> {
+        example_game_state_elements().iter()
+            .map(|e| State::try_from(e).unwrap())
+            .collect()
+    }
 
     #[test]
     fn test_xml_parses() {
@@ -784,8 +1198,8 @@ mod tests {
                 </ship>
             </state>
         "#}, State {
-            board: Board {
-                segments: vec![
+            board: Board::new(
+                vec![
                     Segment {
                         direction: CubeDir::Right,
                         center: CubeVec::ZERO,
@@ -820,8 +1234,8 @@ mod tests {
                         ],
                     },
                 ],
-                next_direction: CubeDir::DownRight,
-            },
+                CubeDir::DownRight,
+            ),
             ships: [
                 Ship {
                     team: Team::One,
@@ -852,20 +1266,273 @@ mod tests {
             last_move: None,
             start_team: Team::One,
             current_team: Team::One,
-        });
+            key: 0,
+        }.with_computed_key());
     }
 
     #[test]
-    fn test_sensible_moves() {
-        let manifest_dir = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap();
-        let game_dir = manifest_dir.join("test-resources").join("example-game");
+    fn test_xml_formats() {
+        for element in example_game_state_elements() {
+            let state = State::try_from(&element).unwrap();
+            ::pretty_assertions::assert_eq!(Element::from(state), element);
+        }
+    }
 
-        create_dir_all(&game_dir).unwrap();
-        let mut entries: Vec<DirEntry> = game_dir.read_dir().unwrap()
-            .map(|e| e.unwrap())
-            .collect();
-    
-        entries.sort_by_key(|e| e.file_name());
+    #[test]
+    fn test_xml_roundtrips() {
+        let states = example_game_states();
+        assert!(!states.is_empty());
+        for state in states {
+            assert_xml_roundtrip!(state);
+        }
+    }
+
+    #[test]
+    fn test_key_is_maintained() {
+        let mut states = example_game_states();
+        states.extend((0..5).map(|seed| State::new(Board::generate(seed))));
+        for state in states {
+            assert_eq!(state.key(), state.compute_key());
+            let mut positions = HashMap::new();
+            for m in state.possible_moves().take(200) {
+                let child = state.child(m.clone()).unwrap();
+                assert_eq!(child.key(), child.compute_key(), "Key diverged after {:?}", m);
+                let position = (child.ships(), child.current_team());
+                assert_eq!(positions.entry(child.key()).or_insert(position), &position);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_unmake() {
+        let mut states = example_game_states();
+        states.extend((0..5).map(|seed| State::new(Board::generate(seed))));
+        for mut state in states {
+            let original = state.clone();
+            for m in original.possible_moves().take(200).chain(original.sensible_moves()) {
+                let undo = state.make(&m).unwrap();
+                assert_eq!(state, original.child(m.clone()).unwrap(), "make differs from child for {:?}", m);
+                state.unmake(undo);
+                assert_eq!(state, original, "unmake didn't restore the state after {:?}", m);
+            }
+            assert!(state.make(&Move::new()).is_err());
+            assert_eq!(state, original);
+
+            let mut undos = Vec::new();
+            while !state.is_over() {
+                let m = state.sensible_moves().pop().unwrap();
+                undos.push(state.make(&m).unwrap());
+            }
+            while let Some(undo) = undos.pop() {
+                state.unmake(undo);
+            }
+            assert_eq!(state, original);
+        }
+    }
+
+    #[test]
+    fn test_make_doesnt_clone_board() {
+        let mut states = example_game_states();
+        states.extend((0..5).map(|seed| State::new(Board::generate(seed))));
+        for mut state in states {
+            let moves: Vec<Move> = state.possible_moves().take(200).collect();
+            let clones = BOARD_CLONES.with(|c| c.get());
+            for m in &moves {
+                let undo = state.make(m).unwrap();
+                state.unmake(undo);
+            }
+            assert_eq!(BOARD_CLONES.with(|c| c.get()), clones);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        for state in example_game_states() {
+            for m in state.sensible_moves() {
+                let outcome = state.validate(&m).unwrap();
+                let child = state.child(m).unwrap();
+                assert_eq!(outcome.ship, child.ship(state.current_team()));
+                assert_eq!(outcome.next_team, child.current_team());
+                assert_eq!(outcome.coal_used, state.current_ship().coal - child.ship(state.current_team()).coal);
+            }
+        }
+
+        let state = State::new(Board::generate(0));
+        let ship = state.current_ship();
+
+        let report = state.validate(&Move::new()).unwrap_err();
+        assert_eq!((report.action_index, report.mistake), (None, MoveMistake::NoActions));
+
+        let report = state.validate(&Move::from_iter([Action::advance(1), Action::accelerate(1)])).unwrap_err();
+        assert_eq!((report.action_index, report.mistake), (Some(1), MoveMistake::FirstActionAccelerate));
+        assert_eq!(report.ship.position, ship.position + ship.direction);
+        assert_eq!(report.ship.movement, 0);
+
+        let report = state.validate(&Move::from_iter([Action::turn(CubeDir::DownRight), Action::advance(2)])).unwrap_err();
+        assert_eq!(report.action_index, Some(1));
+        assert_eq!(report.action, Some(Action::advance(2)));
+        assert_eq!(report.problem(), Some(ActionProblem::Advance(AdvanceProblem::MovementPointsMissing { distance: Some(2), movement: 1 })));
+        assert_eq!(report.ship.direction, CubeDir::DownRight);
+
+        // Consecutive advances are reported as the merged action
+        let report = state.validate(&Move::from_iter([Action::turn(CubeDir::DownRight), Action::advance(1), Action::advance(1)])).unwrap_err();
+        assert_eq!(report.action_index, Some(1));
+        assert_eq!(report.action, Some(Action::advance(2)));
+
+        let report = state.validate(&Move::from_iter([Action::accelerate(1), Action::advance(1)])).unwrap_err();
+        assert_eq!((report.action_index, report.mistake), (None, MoveMistake::MovementPointsLeft(1)));
+    }
+
+    #[test]
+    fn test_sandbanks() {
+        let mut state = State::new(Board::generate(1));
+        let ship = state.current_ship();
+        state.board[ship.position + CubeVec::from(ship.direction) * 2] = Field::Sandbank;
+
+        // Running onto a sandbank stops the ship, even with movement points left
+        for acc in [1, 2] {
+            let child = state.child(Move::from_iter([Action::accelerate(acc), Action::advance(2)])).unwrap();
+            let moved = child.ship(Team::One);
+            assert_eq!(moved.position, ship.position + CubeVec::from(ship.direction) * 2);
+            assert_eq!((moved.speed, moved.movement), (1, 1));
+        }
+        let report = state.validate(&Move::from_iter([Action::accelerate(2), Action::advance(2), Action::turn(CubeDir::DownRight)])).unwrap_err();
+        assert_eq!((report.action_index, report.mistake), (Some(2), MoveMistake::SandbankEnd));
+        assert!(state.possible_moves().any(|m| m.actions.last() == Some(&Action::advance(2))));
+
+        // On a sandbank, the ship has to start by advancing one field in either direction
+        let mut state = State::new(Board::generate(1));
+        let start = ship.position + ship.direction;
+        state.ships[0].position = start;
+        state.board[start] = Field::Sandbank;
+        let state = state.with_computed_key();
+        let moves: Vec<Move> = state.possible_moves().collect();
+        assert!(moves.iter().any(|m| m.actions == [Action::advance(-1)]));
+        for m in moves {
+            let distance = match m.actions[..] {
+                [Action::Advance(adv), ..] if adv.distance.abs() == 1 => adv.distance,
+                _ => panic!("Unexpected move {:?}", m),
+            };
+            assert!(m.actions[1..].iter().all(|a| matches!(a, Action::Turn(_))), "Unexpected move {:?}", m);
+            let moved = state.child(m).unwrap().ship(Team::One);
+            assert_eq!(moved.position, start + CubeVec::from(ship.direction) * distance);
+        }
+        let report = state.validate(&Move::from_iter([Action::accelerate(1), Action::advance(2)])).unwrap_err();
+        assert_eq!(report.problem(), Some(ActionProblem::Accelerate(AccelerateProblem::OnSandbank)));
+        let report = state.validate(&Move::from_iter([Action::turn(CubeDir::DownRight), Action::advance(1)])).unwrap_err();
+        assert_eq!(report.problem(), Some(ActionProblem::Turn(TurnProblem::RotationOnSandbankNotAllowed)));
+
+        // Ships pushed onto a sandbank are slowed down
+        let mut state = State::new(Board::generate(1));
+        state.ships[1].position = ship.position + ship.direction;
+        state.board[ship.position + CubeVec::from(ship.direction) * 2] = Field::Sandbank;
+        let state = state.with_computed_key();
+        let child = state.child(Move::from_iter([Action::accelerate(1), Action::advance(1), Action::push(ship.direction)])).unwrap();
+        let pushed = child.ship(Team::Two);
+        assert_eq!(pushed.position, ship.position + CubeVec::from(ship.direction) * 2);
+        assert_eq!(pushed.speed, 1);
+        assert_eq!(child.key(), child.compute_key());
+
+        // Pushing off a sandbank is not allowed
+        let mut state = State::new(Board::generate(1));
+        state.ships[1].position = ship.position;
+        state.board[ship.position] = Field::Sandbank;
+        let state = state.with_computed_key();
+        assert_eq!(state.child(Action::push(ship.direction)), Err(ActionProblem::Push(PushProblem::SandbankPush)));
+    }
+
+    #[test]
+    fn test_end_reason_and_result() {
+        let states = example_game_states();
+        let (last, running) = states.split_last().unwrap();
+        assert!(running.iter().all(|s| s.end_reason().is_none()));
+        assert_eq!(last.end_reason(), Some(EndReason::TooFarBehind(Team::One)));
+
+        let result = last.result();
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::Two)));
+        assert_eq!(result.definition().fragments().iter().map(|f| f.name()).collect::<Vec<_>>(), ["Siegpunkte", "∅ Punkte"]);
+        assert_eq!(result.scores()[&Player::new(None, Team::One)], Score::new(ScoreCause::Regular, "", [0, 17]));
+        assert_eq!(result.scores()[&Player::new(None, Team::Two)], Score::new(ScoreCause::Regular, "", [2, 35]));
+        assert_eq!(State::result_points(&result, Team::One), Some(17));
+        assert_eq!(State::result_points(&result, Team::Two), Some(35));
+
+        // Results from elsewhere may leave out the points
+        let short = GameResult::new(result.definition().clone(), [(Player::new(None, Team::One), Score::new(ScoreCause::Regular, "", [2]))], None);
+        assert_eq!(State::result_points(&short, Team::One), None);
+        assert_eq!(State::result_points(&short, Team::Two), None);
+
+        let result = last.violation_result(Team::Two, MoveMistake::NoActions);
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::One)));
+        assert_eq!(result.scores()[&Player::new(None, Team::Two)], Score::new(ScoreCause::RuleViolation, "NoActions", [0, 35]));
+
+        let result = last.timeout_result(Team::One);
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::Two)));
+        assert_eq!(result.scores()[&Player::new(None, Team::One)].cause(), ScoreCause::SoftTimeout);
+        assert_eq!(result.scores()[&Player::new(None, Team::Two)].cause(), ScoreCause::Regular);
+
+        // Falling too far behind loses even with more points
+        let mut behind = last.clone();
+        behind.ships[Team::One.index()].points = 50;
+        let result = behind.result();
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::Two)));
+        assert_eq!(result.scores()[&Player::new(None, Team::One)], Score::new(ScoreCause::Regular, "", [0, 50]));
+
+        let mut state = State::new(Board::generate(0));
+        state.turn = 2 * ROUND_LIMIT;
+        assert_eq!(state.end_reason(), Some(EndReason::RoundLimit));
+        let result = state.result();
+        assert_eq!(result.winner(), &None);
+        assert!(result.scores().values().all(|s| s.parts()[0] == 1));
+    }
+
+    #[test]
+    fn test_canonical_moves() {
+        let mut states: Vec<State> = example_game_states().into_iter().step_by(12).collect();
+        states.push(State::new(Board::generate(0)));
+        for state in states {
+            let raw: Vec<Move> = state.possible_moves().collect();
+            let canonical = state.canonical_moves();
+            assert!(!canonical.is_empty());
+            assert_eq!(canonical.iter().map(|c| c.folded).sum::<usize>(), raw.len());
+
+            let mut cheapest = HashMap::new();
+            for m in raw {
+                let child = state.child(m).unwrap();
+                let coal_used = state.current_ship().coal - child.ship(state.current_team()).coal;
+                let entry = cheapest.entry(child.canonical_key()).or_insert(coal_used);
+                *entry = coal_used.min(*entry);
+            }
+            assert_eq!(cheapest.len(), canonical.len());
+            for c in canonical {
+                let child = state.child(c.m.clone()).unwrap();
+                assert_eq!(child.key(), c.key);
+                assert_eq!(cheapest[&child.canonical_key()], c.coal_used);
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_moves_fold_coal() {
+        let state = State::new(Board::generate(0));
+        let cheap = Move::from_iter([Action::accelerate(1), Action::advance(1), Action::turn(CubeDir::Left), Action::advance(1), Action::turn(CubeDir::DownLeft)]);
+        let expensive = Move::from_iter([Action::accelerate(1), Action::turn(CubeDir::UpLeft), Action::advance(1), Action::turn(CubeDir::DownRight), Action::advance(1), Action::turn(CubeDir::DownLeft)]);
+
+        // Both moves end in the same position, just with different coal left
+        let (cheap_child, expensive_child) = (state.child(cheap.clone()).unwrap(), state.child(expensive.clone()).unwrap());
+        assert_ne!(cheap_child.key(), expensive_child.key());
+        assert_eq!(cheap_child.canonical_key(), expensive_child.canonical_key());
+
+        let canonical = state.canonical_moves();
+        let folded = canonical.iter().find(|c| c.m == cheap).unwrap();
+        assert!(folded.folded > 1);
+        assert_eq!(folded.key, cheap_child.key());
+        assert_eq!(folded.coal_used, state.current_ship().coal - cheap_child.ship(Team::One).coal);
+        assert!(canonical.iter().all(|c| c.m != expensive));
+    }
+
+    #[test]
+    fn test_sensible_moves() {
+        let entries = example_game_entries();
 
         macro_rules! assert_moves_valid {
             ($state:expr, $moves:expr $(, $args:expr)*) => {
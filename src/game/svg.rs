@@ -0,0 +1,342 @@
+use std::f64::consts::PI;
+
+use crate::util::{Element, ElementBuilder, Perform};
+
+use super::{Action, CubeDir, CubeVec, Field, Ship, State, Team};
+
+/// The distance from a hex's center to its corners in pixels.
+const HEX_SIZE: f64 = 20.0;
+/// The space around the board in pixels.
+const MARGIN: f64 = 30.0;
+
+/// Renders game states as SVG images with pointy-top hexes.
+///
+/// If the previous state is given, the path of the last move is drawn on
+/// top of the board.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SvgRenderer<'a> {
+    previous: Option<&'a State>,
+}
+
+impl<'a> SvgRenderer<'a> {
+    /// Creates a renderer without a move overlay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the state the last move was performed on, which enables
+    /// drawing its path.
+    pub fn previous(mut self, previous: Option<&'a State>) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    /// Renders the given state as a standalone SVG document.
+    pub fn render(&self, state: &State) -> String {
+        self.render_element(state).to_string()
+    }
+
+    /// Renders the given state as an SVG element.
+    pub fn render_element(&self, state: &State) -> Element {
+        let board = state.board();
+        let pixels: Vec<(f64, f64)> = board.fields().map(|(p, _)| Self::pixel(p)).collect();
+        let min_x = pixels.iter().map(|p| p.0).fold(f64::INFINITY, f64::min) - MARGIN;
+        let min_y = pixels.iter().map(|p| p.1).fold(f64::INFINITY, f64::min) - MARGIN - 20.0;
+        let max_x = pixels.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max) + MARGIN;
+        let max_y = pixels.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max) + MARGIN;
+        let (width, height) = (max_x - min_x, max_y - min_y);
+
+        let [one, two] = state.ships();
+        let title = format!(
+            "Turn {}: {} {} points, {} passengers / {} {} points, {} passengers",
+            state.turn(), one.team, one.points, one.passengers, two.team, two.points, two.passengers,
+        );
+
+        Element::new("svg")
+            .attribute("xmlns", "http://www.w3.org/2000/svg")
+            .attribute("viewBox", format!("{:.1} {:.1} {:.1} {:.1}", min_x, min_y, width, height))
+            .attribute("width", format!("{:.0}", width))
+            .attribute("height", format!("{:.0}", height))
+            .child(Self::defs())
+            .child(Element::new("rect")
+                .attribute("x", format!("{:.1}", min_x))
+                .attribute("y", format!("{:.1}", min_y))
+                .attribute("width", format!("{:.1}", width))
+                .attribute("height", format!("{:.1}", height))
+                .attribute("fill", "#1d3557"))
+            .child(Element::new("text")
+                .attribute("x", format!("{:.1}", min_x + 10.0))
+                .attribute("y", format!("{:.1}", min_y + 20.0))
+                .attribute("fill", "#ffffff")
+                .attribute("font-family", "sans-serif")
+                .attribute("font-size", 14)
+                .content(&title))
+            .childs(board.fields().map(|(p, f)| self.field(state, p, f)))
+            .childs(self.move_path(state))
+            .childs(state.ships().into_iter().map(Self::ship))
+            .build()
+    }
+
+    /// The marker definitions used for arrows.
+    fn defs() -> ElementBuilder<'static> {
+        Element::new("defs").childs(["#ffffff", "#e03131", "#4dabf7"].into_iter().enumerate().map(|(i, color)| {
+            Element::new("marker")
+                .attribute("id", format!("arrow{}", i))
+                .attribute("viewBox", "0 0 10 10")
+                .attribute("refX", 8)
+                .attribute("refY", 5)
+                .attribute("markerWidth", 4)
+                .attribute("markerHeight", 4)
+                .attribute("orient", "auto-start-reverse")
+                .child(Element::new("path").attribute("d", "M 0 0 L 10 5 L 0 10 z").attribute("fill", color))
+                .build()
+        }))
+    }
+
+    /// Draws the hex of a field, including its passenger or current arrow.
+    fn field(&self, state: &State, position: CubeVec, field: Field) -> Element {
+        let (x, y) = Self::pixel(position);
+        let board = state.board();
+        let current = board.current_direction_at(position).filter(|_| field == Field::Water);
+        let fill = match field {
+            Field::Water if current.is_some() => "#74c0fc",
+            Field::Water => "#4d8fd6",
+            Field::Island => "#5c940d",
+            Field::Sandbank => "#f0d78c",
+            Field::Goal => "#fab005",
+            Field::Passenger { .. } => "#a9713e",
+        };
+        let corners = (0..6)
+            .map(|i| {
+                let angle = PI / 180.0 * (60.0 * i as f64 - 30.0);
+                format!("{:.1},{:.1}", x + HEX_SIZE * angle.cos(), y + HEX_SIZE * angle.sin())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut group = Element::new("g")
+            .child(Element::new("polygon")
+                .attribute("points", corners)
+                .attribute("fill", fill)
+                .attribute("stroke", "#1d3557")
+                .attribute("stroke-width", 1.5));
+
+        if let Some(direction) = current {
+            group = group.child(Self::arrow(position, direction, 0.5).attribute("opacity", 0.6));
+        }
+        if let Field::Passenger { direction, passenger } = field {
+            group = group
+                .child(Self::arrow(position, direction, 0.7))
+                .child(Element::new("text")
+                    .attribute("x", format!("{:.1}", x))
+                    .attribute("y", format!("{:.1}", y + 5.0))
+                    .attribute("text-anchor", "middle")
+                    .attribute("fill", "#ffffff")
+                    .attribute("font-family", "sans-serif")
+                    .attribute("font-size", 13)
+                    .content(&passenger.to_string()));
+        }
+
+        group.build()
+    }
+
+    /// Draws a ship as a triangle pointing in its direction.
+    fn ship(ship: Ship) -> Element {
+        let (x, y) = Self::pixel(ship.position);
+        let angle = Self::angle(ship.direction);
+        let corners = [(0.65, 0.0), (-0.45, 0.45), (-0.2, 0.0), (-0.45, -0.45)]
+            .into_iter()
+            .map(|(dx, dy)| {
+                let (dx, dy) = (dx * HEX_SIZE, dy * HEX_SIZE);
+                format!("{:.1},{:.1}", x + dx * angle.cos() - dy * angle.sin(), y + dx * angle.sin() + dy * angle.cos())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Element::new("polygon")
+            .attribute("points", corners)
+            .attribute("fill", Self::team_color(ship.team))
+            .attribute("stroke", "#ffffff")
+            .attribute("stroke-width", 1.5)
+            .build()
+    }
+
+    /// Draws the path of the last move by replaying it on the previous
+    /// state. Nothing is drawn if that isn't possible.
+    fn move_path(&self, state: &State) -> Vec<Element> {
+        let (Some(previous), Some(m)) = (self.previous, state.last_move()) else { return Vec::new() };
+        let team = previous.current_team();
+        let other = team.opponent();
+        let mut current = previous.clone();
+        let mut path = vec![current.ship(team).position];
+        let mut pushes = Vec::new();
+
+        for &action in &m.actions {
+            let other_position = current.ship(other).position;
+            if current.perform(action).is_err() {
+                return Vec::new();
+            }
+            match action {
+                Action::Advance(_) => path.push(current.ship(team).position),
+                Action::Push(_) => pushes.push((other_position, current.ship(other).position)),
+                _ => {},
+            }
+        }
+
+        let marker = |team: Team| format!("url(#arrow{})", team.index() + 1);
+        let line = |points: &[CubeVec], team: Team| Element::new("polyline")
+            .attribute("points", points.iter().map(|&p| {
+                let (x, y) = Self::pixel(p);
+                format!("{:.1},{:.1}", x, y)
+            }).collect::<Vec<_>>().join(" "))
+            .attribute("fill", "none")
+            .attribute("stroke", Self::team_color(team))
+            .attribute("stroke-width", 3)
+            .attribute("stroke-dasharray", "6 4")
+            .attribute("marker-end", marker(team))
+            .build();
+
+        let mut elements = Vec::new();
+        if path.len() > 1 {
+            elements.push(line(&path, team));
+        }
+        elements.extend(pushes.into_iter().map(|(from, to)| line(&[from, to], other)));
+        elements
+    }
+
+    /// Draws a white arrow from the center of the given field in the given direction.
+    fn arrow(position: CubeVec, direction: CubeDir, length: f64) -> ElementBuilder<'static> {
+        let (x, y) = Self::pixel(position);
+        let angle = Self::angle(direction);
+        let (dx, dy) = (angle.cos() * HEX_SIZE * length, angle.sin() * HEX_SIZE * length);
+        Element::new("line")
+            .attribute("x1", format!("{:.1}", x - dx * 0.5))
+            .attribute("y1", format!("{:.1}", y - dy * 0.5))
+            .attribute("x2", format!("{:.1}", x + dx))
+            .attribute("y2", format!("{:.1}", y + dy))
+            .attribute("stroke", "#ffffff")
+            .attribute("stroke-width", 2)
+            .attribute("marker-end", "url(#arrow0)")
+    }
+
+    /// The fill color of the given team's ship.
+    fn team_color(team: Team) -> &'static str {
+        match team {
+            Team::One => "#e03131",
+            Team::Two => "#4dabf7",
+        }
+    }
+
+    /// The center of the given field in pixels.
+    fn pixel(position: CubeVec) -> (f64, f64) {
+        let (q, r) = (position.q() as f64, position.r() as f64);
+        (HEX_SIZE * 3f64.sqrt() * (q + r / 2.0), HEX_SIZE * 1.5 * r)
+    }
+
+    /// The angle of the given direction in radians, clockwise from the x-axis.
+    fn angle(direction: CubeDir) -> f64 {
+        let (x, y) = Self::pixel(CubeVec::from(direction));
+        y.atan2(x)
+    }
+}
+
+/// Renders a sequence of states as a self-contained HTML page that steps
+/// through the SVG frames with buttons or the arrow keys.
+pub fn html_replay(states: &[State], title: &str) -> String {
+    let frames: String = svg_frames(states).into_iter()
+        .enumerate()
+        .map(|(i, svg)| format!("<div class=\"frame\"{}>{}</div>\n", if i == 0 { "" } else { " hidden" }, svg))
+        .collect();
+    let title = Element::new("title").content(title).build();
+
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+{title}
+<style>
+body {{ background: #0b1d33; color: #ffffff; font-family: sans-serif; }}
+svg {{ max-width: 100%; height: auto; }}
+</style>
+</head>
+<body>
+<div><button id="prev">&larr;</button> <span id="counter"></span> <button id="next">&rarr;</button></div>
+{frames}<script>
+const frames = document.querySelectorAll('.frame');
+let index = 0;
+function show(i) {{
+  frames[index].hidden = true;
+  index = Math.max(0, Math.min(frames.length - 1, i));
+  frames[index].hidden = false;
+  document.getElementById('counter').textContent = (index + 1) + ' / ' + frames.length;
+}}
+document.getElementById('prev').onclick = () => show(index - 1);
+document.getElementById('next').onclick = () => show(index + 1);
+document.addEventListener('keydown', e => {{
+  if (e.key === 'ArrowLeft') show(index - 1);
+  if (e.key === 'ArrowRight') show(index + 1);
+}});
+show(0);
+</script>
+</body>
+</html>
+"#)
+}
+
+/// Renders each of the given states as an SVG image. Where a state directly
+/// follows the one before, the path of its last move is drawn.
+pub fn svg_frames(states: &[State]) -> Vec<String> {
+    states.iter()
+        .enumerate()
+        .map(|(i, state)| {
+            let previous = i.checked_sub(1)
+                .map(|j| &states[j])
+                .filter(|p| p.turn() + 1 == state.turn());
+            SvgRenderer::new().previous(previous).render(state)
+        })
+        .collect()
+}
+
+impl State {
+    /// Renders the state as an SVG image.
+    pub fn to_svg(&self) -> String {
+        SvgRenderer::new().render(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::{Action, Board, Move, State}, util::{Element, Perform}};
+
+    use super::{html_replay, svg_frames, SvgRenderer};
+
+    #[test]
+    fn test_to_svg() {
+        let state = State::new(Board::generate(0));
+        let svg: Element = state.to_svg().parse().unwrap();
+        assert_eq!(svg.name(), "svg");
+        let groups = svg.childs_by_name("g").count();
+        assert_eq!(groups, state.board().fields().count());
+        assert_eq!(svg.childs_by_name("polygon").count(), 2);
+        assert_eq!(svg.childs_by_name("polyline").count(), 0);
+    }
+
+    #[test]
+    fn test_move_path() {
+        let state = State::new(Board::generate(1));
+        let child = state.child(Move::from_iter([Action::accelerate(1), Action::advance(2)])).unwrap();
+        let svg: Element = SvgRenderer::new().previous(Some(&state)).render(&child).parse().unwrap();
+        let path = svg.child_by_name("polyline").unwrap();
+        assert_eq!(path.attribute("points").unwrap().split(' ').count(), 2);
+
+        let frames = svg_frames(&[state.clone(), child.clone()]);
+        assert_eq!(frames.len(), 2);
+        assert!(!frames[0].contains("polyline"));
+        assert!(frames[1].contains("polyline"));
+
+        let html = html_replay(&[state, child], "Test <game>");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(html.contains("<title>Test &lt;game&gt;</title>"));
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use super::{Action, ActionProblem, MoveMistake, Ship, Team};
+
+/// The effects of a valid move, as reported by `State::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// The moving ship after the move.
+    pub ship: Ship,
+    /// The other ship after the move.
+    pub other_ship: Ship,
+    /// The amount of coal the move consumed.
+    pub coal_used: i32,
+    /// The number of passengers the moving ship picked up.
+    pub passengers_picked_up: usize,
+    /// The team to move next.
+    pub next_team: Team,
+}
+
+/// Diagnostics for an invalid move, as reported by `State::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveValidationReport {
+    /// The index of the failing action within the move. This is `None` if
+    /// the mistake concerns the move as a whole, e.g. unused movement points.
+    /// For merged advances, this is the index of the first one.
+    pub action_index: Option<usize>,
+    /// The failing action, with consecutive advances merged as they are
+    /// performed.
+    pub action: Option<Action>,
+    /// The mistake.
+    pub mistake: MoveMistake,
+    /// The moving ship at the point of failure, i.e. before the failing
+    /// action or after all actions.
+    pub ship: Ship,
+}
+
+impl MoveValidationReport {
+    /// The problem with the failing action, if any.
+    pub fn problem(&self) -> Option<ActionProblem> {
+        match self.mistake {
+            MoveMistake::ActionFailed(problem) => Some(problem),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MoveValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.action_index, self.action) {
+            (Some(i), Some(action)) => write!(f, "Action #{} ({:?}) failed with {:?}", i, action, self.mistake)?,
+            _ => write!(f, "Move failed with {:?}", self.mistake)?,
+        }
+        write!(f, " (ship at {} with movement {} and coal {})", self.ship.position, self.ship.movement, self.ship.coal)
+    }
+}
@@ -0,0 +1,64 @@
+//! Zobrist keys for the components of a game state. Rather than storing
+//! tables of random numbers, the keys are derived by mixing a unique id of
+//! each component with SplitMix64, which gives equally well-distributed
+//! keys for the unbounded coordinates of the board.
+
+use super::{CubeVec, Ship, Team};
+
+/// A hashed component of the state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u64)]
+enum Component {
+    Position = 1,
+    Direction,
+    Speed,
+    Coal,
+    Passengers,
+    FreeTurns,
+    Movement,
+    FreeAcc,
+    CurrentTeam,
+    PassengerField,
+}
+
+/// Scrambles the given value using the SplitMix64 finalizer.
+const fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// The key for a component having the given value.
+fn component_key(component: Component, team: Team, value: u64) -> u64 {
+    mix(((component as u64) << 56) ^ ((team.index() as u64) << 52) ^ value)
+}
+
+/// Packs the given position into the lower 32 bits.
+fn position_bits(position: CubeVec) -> u64 {
+    ((position.q() as u16 as u64) << 16) | (position.r() as u16 as u64)
+}
+
+/// The key for the given ship.
+pub(crate) fn ship_key(ship: Ship) -> u64 {
+    let key = |component, value: i32| component_key(component, ship.team, value as u32 as u64);
+    component_key(Component::Position, ship.team, position_bits(ship.position))
+        ^ key(Component::Direction, ship.direction.turns())
+        ^ key(Component::Speed, ship.speed)
+        ^ key(Component::Coal, ship.coal)
+        ^ key(Component::Passengers, ship.passengers as i32)
+        ^ key(Component::FreeTurns, ship.free_turns)
+        ^ key(Component::Movement, ship.movement)
+        ^ key(Component::FreeAcc, ship.free_acc)
+}
+
+/// The key for the given team being the current team.
+pub(crate) fn current_team_key(team: Team) -> u64 {
+    component_key(Component::CurrentTeam, team, 0)
+}
+
+/// The key for a passenger field at the given position with the given
+/// number of passengers.
+pub(crate) fn passenger_field_key(position: CubeVec, passenger: usize) -> u64 {
+    component_key(Component::PassengerField, Team::One, position_bits(position) | ((passenger as u64) << 32))
+}
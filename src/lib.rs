@@ -1,4 +1,8 @@
 pub mod client;
 pub mod protocol;
+pub mod referee;
+pub mod replay;
+pub mod search;
+pub mod tournament;
 pub mod game;
 pub mod util;
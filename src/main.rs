@@ -1,12 +1,21 @@
 mod logic;
+#[cfg(feature = "terminal")]
+mod viewer;
 
-use std::str::FromStr;
-use clap::Parser;
+use std::{fs, path::{Path, PathBuf}, str::FromStr, time::Duration};
+use clap::{Parser, Subcommand};
 use simplelog::{SimpleLogger, Config};
 use log::LevelFilter;
-use socha_client_2024::client::{GameClient, DebugMode};
+use socha_client_2024::client::{GameClient, DebugMode, NETWORK_MARGIN};
+use socha_client_2024::game::{Board, Rules, State, Team, perft, perft_divide, html_replay, svg_frames};
+use socha_client_2024::protocol::GameResult;
+use socha_client_2024::replay::Replay;
+use socha_client_2024::search::FeatureEvaluator;
+use socha_client_2024::util::{Element, Error, Result};
 
 use logic::OwnLogic;
+#[cfg(feature = "terminal")]
+use viewer::Viewer;
 
 /// Software Challenge 2024 client.
 #[derive(Parser, Debug)]
@@ -30,9 +39,154 @@ struct Args {
     /// Prints outgoing XML messages to the console for debugging.
     #[clap(short = 'D', long)]
     debug_writer: bool,
+    /// The time in milliseconds reserved for sending a move to the server.
+    #[clap(long, default_value_t = NETWORK_MARGIN.as_millis() as u64)]
+    network_margin: u64,
+    /// Lets the logic keep searching while the opponent is to move.
+    #[clap(long)]
+    ponder: bool,
     /// Prints this help.
     #[clap(long, action = clap::ArgAction::HelpLong)]
     help: Option<bool>,
+    /// A tool to run instead of the client.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Tools for working with game states offline.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Counts the leaf positions reachable by the generated moves.
+    Perft {
+        /// The depth to count positions at.
+        depth: usize,
+        /// A state XML file to start from instead of a generated board.
+        #[clap(long)]
+        state: Option<PathBuf>,
+        /// The seed of the generated board to start from.
+        #[clap(long, default_value_t = 0)]
+        seed: u64,
+        /// Places sandbanks on the generated board.
+        #[clap(long)]
+        sandbanks: bool,
+        /// Breaks the count down by the first move.
+        #[clap(long)]
+        divide: bool,
+    },
+    /// Steps through a replay file or a directory of state XML files.
+    #[cfg(feature = "terminal")]
+    Viewer {
+        /// The replay file or state directory to open.
+        path: PathBuf,
+    },
+    /// Exports a state, a replay or a directory of state XML files as SVG.
+    Svg {
+        /// The state file, replay file or state directory to export.
+        path: PathBuf,
+        /// The file (or, for multiple frames, the directory) to write to
+        /// instead of stdout.
+        #[clap(long)]
+        out: Option<PathBuf>,
+        /// Writes all frames to a single HTML page.
+        #[clap(long)]
+        html: bool,
+    },
+    /// Shows how the evaluation of a state is made up, for both teams.
+    Eval {
+        /// The state file, replay file or state directory to evaluate the
+        /// last state of.
+        path: PathBuf,
+        /// The feature weights, e.g. `progress=10,passengers=60`. Omitted
+        /// features keep their default weight.
+        #[clap(long, default_value = "")]
+        weights: String,
+    },
+}
+
+/// Loads a state from the given XML file, or creates one on a generated board.
+fn load_state(path: Option<&PathBuf>, seed: u64, rules: Rules) -> Result<State> {
+    match path {
+        Some(path) => State::try_from(&Element::from_str(&fs::read_to_string(path)?)?),
+        None => Ok(State::new(Board::generate_with_rules(seed, rules))),
+    }
+}
+
+/// Loads the states of a game from a replay file, a single `*.state.xml`
+/// file or a directory of them, along with the result if known.
+fn load_states(path: &Path) -> Result<(Vec<State>, Option<GameResult>)> {
+    if path.is_dir() {
+        let mut paths = fs::read_dir(path)?
+            .map(|e| Ok(e?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        paths.retain(|p| p.to_string_lossy().ends_with(".state.xml"));
+        paths.sort();
+        let states = paths.iter()
+            .map(|p| load_state(Some(p), 0, Rules::default()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok((states, None))
+    } else if path.to_string_lossy().ends_with(".state.xml") {
+        Ok((vec![load_state(Some(&path.to_path_buf()), 0, Rules::default())?], None))
+    } else {
+        let replay = Replay::load(path)?;
+        Ok((replay.states, replay.result))
+    }
+}
+
+/// Writes the given text to the file or stdout.
+fn write_output(out: Option<&PathBuf>, text: &str) -> Result<()> {
+    match out {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Runs the given tool.
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Perft { depth, state, seed, sandbanks, divide } => {
+            let state = load_state(state.as_ref(), seed, Rules { sandbanks })?;
+            if divide {
+                let counts = perft_divide(&state, depth);
+                for (m, count) in &counts {
+                    println!("{}: {}", m, count);
+                }
+                println!("Total: {}", counts.iter().map(|(_, c)| c).sum::<u64>());
+            } else {
+                println!("{}", perft(&state, depth));
+            }
+        },
+        #[cfg(feature = "terminal")]
+        Command::Viewer { path } => {
+            let (states, result) = load_states(&path)?;
+            Viewer::new(states, result).run()?;
+        },
+        Command::Svg { path, out, html } => {
+            let (states, _) = load_states(&path)?;
+            let title = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            if html {
+                write_output(out.as_ref(), &html_replay(&states, &title))?;
+            } else if let [state] = &states[..] {
+                write_output(out.as_ref(), &state.to_svg())?;
+            } else {
+                let dir = out.ok_or_else(|| Error::Custom("Exporting multiple frames requires --out <directory>".to_owned()))?;
+                fs::create_dir_all(&dir)?;
+                for (i, svg) in svg_frames(&states).iter().enumerate() {
+                    fs::write(dir.join(format!("{:03}.svg", i)), svg)?;
+                }
+            }
+        },
+        Command::Eval { path, weights } => {
+            let (states, _) = load_states(&path)?;
+            let state = states.last().ok_or_else(|| Error::InvalidState("No states to evaluate".to_owned()))?;
+            let evaluator: FeatureEvaluator = weights.parse()?;
+            println!("Turn {}, {} to move, weights {}", state.turn(), state.current_team(), evaluator);
+            for team in Team::ALL {
+                println!("\nTeam {}:\n{}", team, evaluator.breakdown(state, team));
+            }
+        },
+    }
+    Ok(())
 }
 
 fn main() {
@@ -41,6 +195,12 @@ fn main() {
     
     // Set up logging
     SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    // Run a tool if requested
+    if let Some(command) = args.command {
+        run_command(command).expect("Error while running command.");
+        return;
+    }
     
     // Setup the client and the delegate
     let debug_mode = DebugMode {
@@ -48,6 +208,8 @@ fn main() {
         debug_writer: args.debug_writer,
     };
 
-    let client = GameClient::new(OwnLogic, debug_mode, args.reservation);
+    let client = GameClient::new(OwnLogic, debug_mode, args.reservation)
+        .network_margin(Duration::from_millis(args.network_margin))
+        .ponder(args.ponder);
     let _result = client.connect(&args.host, args.port).expect("Error while running client.");
 }
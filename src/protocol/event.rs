@@ -4,6 +4,7 @@ use super::EventPayload;
 
 /// A message from the server.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Event {
     /// Notifies the client that they successfully joined a room.
     Joined { room_id: String },
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::client::{BestMove, Deadline, GameClientDelegate};
+use crate::game::{State, Team, MoveMistake};
+use crate::protocol::GameResult;
+use crate::util::Perform;
+
+/// Why a team lost a game before it was over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Forfeit {
+    /// The team performed an invalid move.
+    Violation(MoveMistake),
+    /// The team took longer than the move time.
+    Timeout,
+}
+
+/// Plays a game between two delegates in-process, applying the rules
+/// locally instead of going through the game server.
+pub struct Referee<One, Two> where One: GameClientDelegate, Two: GameClientDelegate {
+    one: One,
+    two: Two,
+    move_time: Option<Duration>,
+    forfeit: Option<(Team, Forfeit)>,
+}
+
+impl<One, Two> Referee<One, Two> where One: GameClientDelegate, Two: GameClientDelegate {
+    /// Creates a new referee with the delegates playing team one and two.
+    pub fn new(one: One, two: Two) -> Self {
+        Self { one, two, move_time: None, forfeit: None }
+    }
+
+    /// Limits the time per move. The delegates are asked for moves with a
+    /// deadline, and a team that misses it loses by timeout. Since the
+    /// delegates run on the referee's thread, a move is only checked once
+    /// it was picked.
+    pub fn move_time(mut self, move_time: Duration) -> Self {
+        self.move_time = Some(move_time);
+        self
+    }
+
+    /// How the last game played was forfeited, if it was.
+    pub fn forfeit(&self) -> Option<(Team, Forfeit)> { self.forfeit }
+
+    /// The delegate playing team one.
+    pub fn one(&self) -> &One { &self.one }
+
+    /// The delegate playing team two.
+    pub fn two(&self) -> &Two { &self.two }
+
+    /// Consumes the referee and returns the delegates.
+    pub fn into_inner(self) -> (One, Two) { (self.one, self.two) }
+
+    /// The delegate playing the given team.
+    fn delegate_mut(&mut self, team: Team) -> &mut dyn GameClientDelegate {
+        match team {
+            Team::One => &mut self.one,
+            Team::Two => &mut self.two,
+        }
+    }
+
+    /// Plays a full game starting from the given state and returns the result.
+    /// A move that breaks the rules or misses the deadline ends the game
+    /// immediately.
+    pub fn play(&mut self, initial_state: State) -> GameResult {
+        for team in Team::ALL {
+            self.delegate_mut(team).welcome_received(team);
+        }
+
+        let mut state = initial_state;
+        self.forfeit = None;
+
+        while !state.is_over() {
+            for team in Team::ALL {
+                self.delegate_mut(team).state_updated(&state);
+            }
+
+            let team = state.current_team();
+            let start = Instant::now();
+            let m = match self.move_time {
+                Some(move_time) => self.delegate_mut(team).pick_move_until(&state, team, Deadline::new(start, move_time), &BestMove::default()),
+                None => self.delegate_mut(team).pick_move(&state, team),
+            };
+            if self.move_time.is_some_and(|t| start.elapsed() > t) {
+                warn!("Team {} took {:?} to move", team, start.elapsed());
+                self.forfeit = Some((team, Forfeit::Timeout));
+                break;
+            }
+            match state.child(m.clone()) {
+                Ok(next) => state = next,
+                Err(mistake) => {
+                    warn!("Team {} performed invalid move {:?}: {:?}", team, m, mistake);
+                    self.forfeit = Some((team, Forfeit::Violation(mistake)));
+                    break;
+                },
+            }
+        }
+
+        let result = match self.forfeit {
+            Some((team, Forfeit::Violation(mistake))) => state.violation_result(team, mistake),
+            Some((team, Forfeit::Timeout)) => state.timeout_result(team),
+            None => state.result(),
+        };
+        info!("Game ended after turn {}", state.turn());
+        for team in Team::ALL {
+            self.delegate_mut(team).game_ended(&result);
+        }
+        result
+    }
+}
+
+/// Simple delegates for tests playing games in-process.
+#[cfg(test)]
+pub(crate) mod delegates {
+    use crate::{client::GameClientDelegate, game::{State, Team, Move}};
+
+    /// Plays the first sensible move.
+    pub struct FirstMove;
+
+    impl GameClientDelegate for FirstMove {
+        fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+            state.sensible_moves().into_iter().next().unwrap()
+        }
+    }
+
+    /// Plays a move without actions, which is always invalid.
+    pub struct EmptyMove;
+
+    impl GameClientDelegate for EmptyMove {
+        fn pick_move(&mut self, _state: &State, _my_team: Team) -> Move {
+            Move::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::{client::GameClientDelegate, game::{State, Team, Move, MoveMistake, Board, Rules}, protocol::{ScoreCause, Player}};
+
+    use super::{delegates::{EmptyMove, FirstMove}, Forfeit, Referee};
+
+    struct SlowMove;
+
+    impl GameClientDelegate for SlowMove {
+        fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+            thread::sleep(Duration::from_millis(30));
+            state.sensible_moves().into_iter().next().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_plays_full_game() {
+        for (seed, rules) in (0..10).flat_map(|seed| [(seed, Rules::default()), (seed, Rules::with_sandbanks())]) {
+            let result = Referee::new(FirstMove, FirstMove).play(State::new(Board::generate_with_rules(seed, rules)));
+            assert_eq!(result.scores().len(), Team::COUNT);
+            assert!(result.scores().values().all(|s| s.cause() == ScoreCause::Regular));
+            let win_points: i32 = result.scores().values().map(|s| s.parts()[0]).sum();
+            assert_eq!(win_points, 2);
+        }
+    }
+
+    #[test]
+    fn test_invalid_move_is_rule_violation() {
+        let mut referee = Referee::new(EmptyMove, FirstMove);
+        let result = referee.play(State::new(Board::generate(0)));
+        assert_eq!(referee.forfeit(), Some((Team::One, Forfeit::Violation(MoveMistake::NoActions))));
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::Two)));
+        assert_eq!(result.scores()[&Player::new(None, Team::One)].cause(), ScoreCause::RuleViolation);
+        assert_eq!(result.scores()[&Player::new(None, Team::Two)].cause(), ScoreCause::Regular);
+    }
+
+    #[test]
+    fn test_slow_move_is_timeout() {
+        let mut referee = Referee::new(FirstMove, SlowMove).move_time(Duration::from_millis(10));
+        let result = referee.play(State::new(Board::generate(0)));
+        assert_eq!(referee.forfeit(), Some((Team::Two, Forfeit::Timeout)));
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::One)));
+        assert_eq!(result.scores()[&Player::new(None, Team::Two)].cause(), ScoreCause::SoftTimeout);
+
+        // Without a move time, the game is played out
+        let mut referee = Referee::new(FirstMove, SlowMove);
+        referee.play(State::new(Board::generate(0)));
+        assert_eq!(referee.forfeit(), None);
+    }
+}
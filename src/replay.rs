@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use flate2::read::GzDecoder;
+use log::{debug, warn};
+use quick_xml::Reader;
+use quick_xml::events::Event as XmlEvent;
+use crate::game::{State, Move};
+use crate::protocol::{Event, EventPayload, GameResult};
+use crate::util::{Element, Error, Result, Perform};
+
+/// The magic bytes at the start of gzip-compressed files.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A recorded game, as written by the server.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// The states in the order they occurred.
+    pub states: Vec<State>,
+    /// The moves, where `moves[i]` leads from `states[i]` to `states[i + 1]`.
+    pub moves: Vec<Move>,
+    /// The result, if the replay contains one.
+    pub result: Option<GameResult>,
+}
+
+impl Replay {
+    /// Loads a replay from the given file, which may be gzip-compressed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_from(File::open(path)?)
+    }
+
+    /// Reads a replay from the given `<protocol>` stream, which may be
+    /// gzip-compressed.
+    pub fn read_from(read: impl Read) -> Result<Self> {
+        let mut buffered = BufReader::new(read);
+        if buffered.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Self::read_xml_from(BufReader::new(GzDecoder::new(buffered)))
+        } else {
+            Self::read_xml_from(buffered)
+        }
+    }
+
+    /// Reads a replay from the given uncompressed `<protocol>` stream.
+    fn read_xml_from(read: impl BufRead) -> Result<Self> {
+        let mut buf = Vec::new();
+        let mut reader = Reader::from_reader(read);
+
+        // Read <protocol>
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                XmlEvent::Start(ref start) if start.name().as_ref() == b"protocol" => break,
+                XmlEvent::Eof => return Err(Error::Eof),
+                XmlEvent::Decl(_) | XmlEvent::Text(_) | XmlEvent::Comment(_) => (),
+                e => warn!("Got unexpected event {:?}", e),
+            }
+        }
+
+        // Read the messages up to </protocol>
+        let mut states = Vec::new();
+        let mut result = None;
+        loop {
+            let event_xml = match Element::read_child_from(&mut reader) {
+                Ok(Some(event_xml)) => event_xml,
+                // Files cut off before </protocol> end the replay as well
+                Ok(None) | Err(Error::Eof) => break,
+                Err(e) => return Err(e),
+            };
+
+            match Event::try_from(&event_xml)? {
+                Event::Room { payload: EventPayload::Memento(state), .. } => states.push(state),
+                Event::Room { payload: EventPayload::GameResult(game_result), .. } => result = Some(game_result),
+                event => debug!("Skipping {:?} in replay", event),
+            }
+        }
+
+        let moves = states.windows(2)
+            .map(|w| Self::move_between(&w[0], &w[1]))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { states, moves, result })
+    }
+
+    /// Finds the move leading from one state to the next. This is usually
+    /// the next state's last move, which is missing if a player had to skip
+    /// their turn, though. Since the server reveals segments as the ships
+    /// advance, only the ships and the team to move are compared.
+    fn move_between(state: &State, next: &State) -> Result<Move> {
+        if let Some(m) = next.last_move() {
+            return Ok(m.clone());
+        }
+        state.possible_moves()
+            .find(|m| state.child(m.clone()).map(|c| (c.ships(), c.current_team()) == (next.ships(), next.current_team())).unwrap_or(false))
+            .ok_or_else(|| Error::InvalidState(format!("No move leads from turn {} to turn {}", state.turn(), next.turn())))
+    }
+
+    /// The final state of the game.
+    pub fn last_state(&self) -> Option<&State> {
+        self.states.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::read, io::Write, path::PathBuf, str::FromStr};
+
+    use flate2::{write::GzEncoder, Compression};
+    use indoc::indoc;
+
+    use crate::{game::{Move, Team}, protocol::Player, util::Perform};
+
+    use super::Replay;
+
+    fn replay_path() -> PathBuf {
+        PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap().join("test-resources").join("replay.xml")
+    }
+
+    #[test]
+    fn test_load() {
+        // The first turns of the example game, as written by the server
+        let replay = Replay::load(replay_path()).unwrap();
+        assert_eq!(replay.states.len(), 24);
+        assert_eq!(replay.moves.len(), replay.states.len() - 1);
+        assert_eq!(replay.states[0].turn(), 0);
+
+        // The server reveals further segments, so only the ships can match
+        for (i, m) in replay.moves.iter().enumerate() {
+            let next = replay.states[i].child(m.clone()).unwrap();
+            let expected = &replay.states[i + 1];
+            assert_eq!((next.ships(), next.turn(), next.current_team()), (expected.ships(), expected.turn(), expected.current_team()), "Move {:?} doesn't lead to turn {}", m, i + 1);
+        }
+
+        assert_eq!(replay.last_state().unwrap().turn(), 23);
+        assert!(replay.result.is_none());
+    }
+
+    #[test]
+    fn test_result() {
+        let replay = Replay::read_from(indoc! {r#"
+            <protocol>
+                <room roomId="7a4b2c1e-5f3d-4e8a-9b6c-0d1e2f3a4b5c">
+                    <data class="result">
+                        <definition>
+                            <fragment name="Siegpunkte">
+                                <aggregation>SUM</aggregation>
+                                <relevantForRanking>true</relevantForRanking>
+                            </fragment>
+                            <fragment name="∅ Punkte">
+                                <aggregation>AVERAGE</aggregation>
+                                <relevantForRanking>true</relevantForRanking>
+                            </fragment>
+                        </definition>
+                        <scores>
+                            <entry>
+                                <player name="rad" team="ONE"/>
+                                <score cause="REGULAR" reason="">
+                                    <part>0</part>
+                                    <part>17</part>
+                                </score>
+                            </entry>
+                            <entry>
+                                <player name="blues" team="TWO"/>
+                                <score cause="REGULAR" reason="">
+                                    <part>2</part>
+                                    <part>35</part>
+                                </score>
+                            </entry>
+                        </scores>
+                        <winner team="TWO"/>
+                    </data>
+                </room>
+            </protocol>
+        "#}.as_bytes()).unwrap();
+        assert!(replay.states.is_empty());
+        let result = replay.result.unwrap();
+        assert_eq!(result.winner(), &Some(Player::new(None, Team::Two)));
+        assert_eq!(result.scores()[&Player::new(Some("blues"), Team::Two)].parts(), &[2, 35]);
+    }
+
+    #[test]
+    fn test_load_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&read(replay_path()).unwrap()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let replay = Replay::read_from(compressed.as_slice()).unwrap();
+        let uncompressed = Replay::load(replay_path()).unwrap();
+        assert_eq!(replay.states, uncompressed.states);
+        assert_eq!(replay.moves, uncompressed.moves);
+    }
+
+    #[test]
+    fn test_empty() {
+        let replay = Replay::read_from("<protocol>\n</protocol>".as_bytes()).unwrap();
+        assert!(replay.states.is_empty());
+        assert_eq!(replay.moves, Vec::<Move>::new());
+        assert!(replay.result.is_none());
+    }
+}
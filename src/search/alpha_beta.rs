@@ -0,0 +1,476 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant}};
+
+use log::{debug, info};
+
+use crate::{client::{BestMove, Deadline, GameClientDelegate}, game::{Move, State, Team}};
+
+use super::{score_from_table, score_to_table, Bound, Evaluator, TableEntry, TranspositionTable};
+
+/// The score of a won game. Wins found earlier score higher.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// Which moves the search considers at each position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MoveGeneration {
+    /// One move per distinct position reachable by `State::possible_moves`.
+    #[default]
+    Canonical,
+    /// The moves from `State::sensible_moves`, which are far fewer but miss
+    /// moves using more than one coal.
+    Sensible,
+}
+
+impl MoveGeneration {
+    /// Generates the moves for the given state.
+    pub fn moves(self, state: &State) -> Vec<Move> {
+        match self {
+            Self::Canonical => state.canonical_moves().into_iter().map(|c| c.m).collect(),
+            Self::Sensible => state.sensible_moves(),
+        }
+    }
+}
+
+/// The outcome of a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found.
+    pub best: Move,
+    /// The score of the best move from the perspective of the team to move.
+    pub score: i32,
+    /// The expected line of play starting with the best move.
+    pub pv: Vec<Move>,
+    /// The deepest completed search depth, in moves.
+    pub depth: usize,
+    /// The number of positions visited.
+    pub nodes: u64,
+}
+
+/// The size of the table used by parallel searches that weren't given one.
+const PARALLEL_TABLE_MEGABYTES: usize = 16;
+
+/// A negamax search with alpha-beta pruning and iterative deepening. Since
+/// the team ahead moves first in each round, a team may move twice in a row,
+/// in which case the score isn't negated between the moves.
+#[derive(Debug, Clone)]
+pub struct AlphaBeta<E> {
+    evaluator: E,
+    max_depth: usize,
+    moves: MoveGeneration,
+    table: Option<Arc<TranspositionTable>>,
+    threads: usize,
+    budget: Duration,
+}
+
+/// Signals that the time ran out during a search.
+struct Timeout;
+
+impl<E> AlphaBeta<E> where E: Evaluator + Sync {
+    /// Creates a search using the given evaluator.
+    pub fn new(evaluator: E) -> Self {
+        Self { evaluator, max_depth: 64, moves: MoveGeneration::default(), table: None, threads: 1, budget: Duration::from_secs(1) }
+    }
+
+    /// Sets the depth at which iterative deepening stops.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets which moves are considered.
+    pub fn moves(mut self, moves: MoveGeneration) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    /// Sets a transposition table to order moves and cut off positions that
+    /// were already searched. Principal variations may end early at
+    /// positions whose score came from the table.
+    pub fn table(mut self, table: Arc<TranspositionTable>) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Sets the number of threads to search with. Additional threads run the
+    /// same search in the background, some of them a depth ahead, and help
+    /// only by filling the transposition table, which is created for the
+    /// search if none was set (lazy SMP). Only a single thread searches
+    /// deterministically.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the time to search per move when used as a delegate.
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// The evaluator used at the leaves.
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Searches deeper and deeper until the time budget is used up or the
+    /// maximum depth is reached. The first depth is always completed, so a
+    /// move is returned unless there are none.
+    pub fn search(&self, state: &State, budget: Duration) -> Option<SearchResult> {
+        self.search_with(state, budget, |_| {})
+    }
+
+    /// Searches like `search`, invoking the callback with the result of each
+    /// completed depth.
+    pub fn search_with(&self, state: &State, budget: Duration, mut on_depth: impl FnMut(&SearchResult)) -> Option<SearchResult> {
+        let deadline = Instant::now() + budget;
+        let local;
+        let table = match &self.table {
+            Some(table) => Some(table.as_ref()),
+            None if self.threads > 1 => {
+                local = TranspositionTable::with_megabytes(PARALLEL_TABLE_MEGABYTES);
+                Some(&local)
+            },
+            None => None,
+        };
+        if let Some(table) = table {
+            table.new_search();
+        }
+
+        let stop = AtomicBool::new(false);
+        let (result, helpers) = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads)
+                .map(|i| {
+                    let stop = &stop;
+                    scope.spawn(move || self.deepen(state, deadline, table, Some(stop), 1 + i % 2, &mut |_| {}))
+                })
+                .collect();
+            let result = self.deepen(state, deadline, table, None, 1, &mut on_depth);
+            stop.store(true, Ordering::Relaxed);
+            let helpers: Vec<_> = helpers.into_iter().map(|h| h.join().expect("Helper search panicked")).collect();
+            (result, helpers)
+        });
+
+        // Prefer a helper's result only if it got deeper
+        let nodes = result.1 + helpers.iter().map(|(_, n)| n).sum::<u64>();
+        helpers.into_iter()
+            .filter_map(|(r, _)| r)
+            .fold(result.0, |best, r| match best {
+                Some(best) if best.depth >= r.depth => Some(best),
+                _ => Some(r),
+            })
+            .map(|r| SearchResult { nodes, ..r })
+    }
+
+    /// Runs iterative deepening from the given depth until the deadline, the
+    /// maximum depth or the stop signal, returning the result of the deepest
+    /// iteration and the number of nodes visited. Without a stop signal, the
+    /// first iteration runs to completion regardless of the time.
+    fn deepen(&self, state: &State, deadline: Instant, table: Option<&TranspositionTable>, stop: Option<&AtomicBool>, first_depth: usize, on_depth: &mut dyn FnMut(&SearchResult)) -> (Option<SearchResult>, u64) {
+        let mut search = Search { engine: self, table, deadline: None, stop, nodes: 0 };
+        let mut state = state.clone();
+        let mut root = self.moves.moves(&state);
+        let mut result: Option<SearchResult> = None;
+
+        for depth in first_depth..=self.max_depth {
+            search.deadline = (depth > 1 || stop.is_some()).then_some(deadline);
+            let Some((index, score, pv, complete)) = search.root(&mut state, &root, depth) else { break };
+            debug!("Depth {}: {} ({}) after {} nodes", depth, score, root[index], search.nodes);
+            let current = result.insert(SearchResult { best: root[index].clone(), score, pv, depth: if complete { depth } else { depth - 1 }, nodes: search.nodes });
+            if !complete {
+                break;
+            }
+            on_depth(current);
+            if score.abs() >= WIN_SCORE - depth as i32 {
+                break;
+            }
+            // Search the best move first next time
+            let best = root.remove(index);
+            root.insert(0, best);
+        }
+
+        (result, search.nodes)
+    }
+}
+
+impl<E> GameClientDelegate for AlphaBeta<E> where E: Evaluator + Sync {
+    fn pick_move(&mut self, state: &State, my_team: Team) -> Move {
+        self.pick_move_until(state, my_team, Deadline::after(self.budget), &BestMove::default())
+    }
+
+    fn pick_move_until(&mut self, state: &State, _my_team: Team, deadline: Deadline, best: &BestMove) -> Move {
+        let budget = self.budget.min(deadline.remaining());
+        let result = self.search_with(state, budget, |r| best.report(r.best.clone())).expect("No move found!");
+        info!("Chose move {} with score {} at depth {} in {:?}", result.best, result.score, result.depth, deadline.elapsed());
+        result.best
+    }
+}
+
+/// The state of a running search.
+struct Search<'a, E> {
+    engine: &'a AlphaBeta<E>,
+    table: Option<&'a TranspositionTable>,
+    deadline: Option<Instant>,
+    /// Set when a helper thread should give up.
+    stop: Option<&'a AtomicBool>,
+    nodes: u64,
+}
+
+impl<E> Search<'_, E> where E: Evaluator {
+    /// Searches the root moves to the given depth, returning the index of the
+    /// best move, its score, the principal variation and whether all moves
+    /// were searched. If the time runs out, the best of the moves searched so
+    /// far is returned, which is at least as good as the first one.
+    fn root(&mut self, state: &mut State, moves: &[Move], depth: usize) -> Option<(usize, i32, Vec<Move>, bool)> {
+        let team = state.current_team();
+        let mut best: Option<(usize, i32, Vec<Move>)> = None;
+        let mut alpha = -WIN_SCORE - 1;
+
+        for (i, m) in moves.iter().enumerate() {
+            let Ok(undo) = state.make(m) else { continue };
+            let same_team = state.current_team() == team;
+            let child = if same_team {
+                self.negamax(state, depth - 1, 1, alpha, WIN_SCORE + 1)
+            } else {
+                self.negamax(state, depth - 1, 1, -WIN_SCORE - 1, -alpha).map(|(s, pv)| (-s, pv))
+            };
+            state.unmake(undo);
+
+            let Ok((score, mut pv)) = child else {
+                return best.map(|(i, s, pv)| (i, s, pv, false));
+            };
+            if best.is_none() || score > alpha {
+                alpha = score;
+                pv.insert(0, m.clone());
+                best = Some((i, score, pv));
+            }
+        }
+
+        best.map(|(i, s, pv)| (i, s, pv, true))
+    }
+
+    /// Scores the state from the perspective of the team to move, along with
+    /// the principal variation.
+    fn negamax(&mut self, state: &mut State, depth: usize, ply: usize, mut alpha: i32, mut beta: i32) -> Result<(i32, Vec<Move>), Timeout> {
+        self.nodes += 1;
+        if self.deadline.is_some_and(|d| Instant::now() >= d) || self.stop.is_some_and(|s| s.load(Ordering::Relaxed)) {
+            return Err(Timeout);
+        }
+
+        let team = state.current_team();
+        if state.is_over() {
+            let score = match state.result().winner() {
+                Some(winner) if winner.team() == team => WIN_SCORE - ply as i32,
+                Some(_) => -WIN_SCORE + ply as i32,
+                None => 0,
+            };
+            return Ok((score, Vec::new()));
+        }
+        if depth == 0 {
+            return Ok((self.engine.evaluator.evaluate(state, team), Vec::new()));
+        }
+
+        let table = self.table;
+        let key = TranspositionTable::key(state);
+        let entry = table.and_then(|t| t.probe(key));
+        if let Some(entry) = entry.filter(|e| e.depth as usize >= depth) {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return Ok((score, Vec::new())),
+                Bound::Lower => alpha = alpha.max(score),
+                Bound::Upper => beta = beta.min(score),
+            }
+            if alpha >= beta {
+                return Ok((score, Vec::new()));
+            }
+        }
+
+        let moves = self.engine.moves.moves(state);
+        // Search the best move from the table first
+        let mut order: Vec<usize> = (0..moves.len()).collect();
+        if let Some(best) = entry.and_then(|e| e.best_for(self.engine.moves)).filter(|&b| b < moves.len()) {
+            order[..=best].rotate_right(1);
+        }
+
+        let original_alpha = alpha;
+        let mut best: Option<(i32, Vec<Move>, usize)> = None;
+        for i in order {
+            let m = &moves[i];
+            let Ok(undo) = state.make(m) else { continue };
+            let child = if state.current_team() == team {
+                self.negamax(state, depth - 1, ply + 1, alpha, beta)
+            } else {
+                self.negamax(state, depth - 1, ply + 1, -beta, -alpha).map(|(s, pv)| (-s, pv))
+            };
+            state.unmake(undo);
+            let (score, mut pv) = child?;
+
+            if best.as_ref().is_none_or(|(b, _, _)| score > *b) {
+                pv.insert(0, m.clone());
+                best = Some((score, pv, i));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let Some((score, pv, index)) = best else {
+            return Ok((self.engine.evaluator.evaluate(state, team), Vec::new()));
+        };
+        if let Some(table) = table {
+            let bound = if score <= original_alpha {
+                Bound::Upper
+            } else if score >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.store(key, TableEntry {
+                score: score_to_table(score, ply),
+                bound,
+                depth: depth.min(u8::MAX as usize) as u8,
+                best: u16::try_from(index).ok(),
+                moves: self.engine.moves,
+            });
+        }
+        Ok((score, pv))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::{Duration, Instant}};
+
+    use crate::client::{BestMove, Deadline, GameClientDelegate};
+    use crate::game::{Board, CubeDir, Field, Move, State, NUMBER_OF_SEGMENTS};
+    use crate::search::{Evaluator, Feature, FeatureEvaluator, TranspositionTable};
+
+    use super::{AlphaBeta, MoveGeneration, WIN_SCORE};
+
+    /// Plays the given moves, checking that they are valid.
+    fn play(state: &State, moves: &[Move]) -> State {
+        let mut state = state.clone();
+        for m in moves {
+            state.make(m).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn test_depth_one_picks_best_evaluation() {
+        let state = State::new(Board::generate(4));
+        let evaluator = FeatureEvaluator::new();
+        let result = AlphaBeta::new(evaluator).max_depth(1).moves(MoveGeneration::Sensible).search(&state, Duration::ZERO).unwrap();
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.pv, vec![result.best.clone()]);
+
+        let best = state.sensible_moves().into_iter()
+            .map(|m| evaluator.evaluate(&play(&state, &[m]), state.current_team()))
+            .max()
+            .unwrap();
+        assert_eq!(result.score, best);
+    }
+
+    #[test]
+    fn test_score_matches_pv() {
+        let state = State::new(Board::generate(2));
+        let engine = AlphaBeta::new(FeatureEvaluator::new()).max_depth(3).moves(MoveGeneration::Sensible);
+        let result = engine.search(&state, Duration::from_secs(60)).unwrap();
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.pv[0], result.best);
+
+        // The evaluator is antisymmetric, so the score is the evaluation at
+        // the end of the line for the team at the root
+        let end = play(&state, &result.pv);
+        assert_eq!(result.score, engine.evaluator().evaluate(&end, state.current_team()));
+    }
+
+    #[test]
+    fn test_table_keeps_scores() {
+        for seed in 0..3 {
+            let state = State::new(Board::generate(seed));
+            let engine = AlphaBeta::new(FeatureEvaluator::new()).max_depth(3).moves(MoveGeneration::Sensible);
+            let plain = engine.search(&state, Duration::from_secs(60)).unwrap();
+
+            let table = Arc::new(TranspositionTable::new(1 << 16));
+            let engine = engine.table(table.clone());
+            let first = engine.search(&state, Duration::from_secs(60)).unwrap();
+            assert_eq!(first.score, plain.score);
+            assert!(first.nodes <= plain.nodes);
+            play(&state, &first.pv);
+
+            // Searching again mostly hits the table
+            let second = engine.search(&state, Duration::from_secs(60)).unwrap();
+            assert_eq!(second.score, plain.score);
+            assert!(second.nodes < first.nodes);
+        }
+    }
+
+    #[test]
+    fn test_stops_at_deadline() {
+        let state = State::new(Board::generate(6));
+        let start = Instant::now();
+        let result = AlphaBeta::new(FeatureEvaluator::new()).search(&state, Duration::from_millis(50)).unwrap();
+        assert!(result.depth >= 1);
+        assert!(state.canonical_moves().iter().any(|c| c.m == result.best));
+        // Only the first depth may overrun the budget
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_finds_win() {
+        // The ship carries two passengers next to the goal, facing it, so it
+        // wins at the end of the round by advancing onto it
+        let board = Board::generate(0);
+        let (goal, dock) = board.fields()
+            .filter(|&(_, f)| f == Field::Goal)
+            .flat_map(|(p, _)| CubeDir::ALL.map(|d| (p, d)))
+            .find(|&(p, d)| board.get(p + d) == Some(&Field::Water) && !board.does_field_have_current(p + d))
+            .map(|(p, d)| (p, p + d))
+            .unwrap();
+        let other = board.segments()[NUMBER_OF_SEGMENTS - 3].center;
+        let mut state = State::new(board);
+        let team = state.current_team();
+        {
+            let mut ship = state.ship_mut(team);
+            ship.position = dock;
+            ship.direction = CubeDir::ALL.into_iter().find(|&d| dock + d == goal).unwrap();
+            ship.passengers = 2;
+        }
+        state.ship_mut(team.opponent()).position = other;
+
+        let evaluator = FeatureEvaluator::new().weight(Feature::Progress, 0);
+        let result = AlphaBeta::new(evaluator).max_depth(4).moves(MoveGeneration::Sensible).search(&state, Duration::from_secs(60)).unwrap();
+        assert_eq!(result.score, WIN_SCORE - 2);
+        let end = play(&state, &result.pv);
+        assert!(end.is_over());
+        assert_eq!(end.result().winner().as_ref().map(|w| w.team()), Some(team));
+    }
+
+    #[test]
+    fn test_parallel_keeps_scores() {
+        let state = State::new(Board::generate(9));
+        let engine = AlphaBeta::new(FeatureEvaluator::new()).max_depth(3).moves(MoveGeneration::Sensible);
+        let single = engine.search(&state, Duration::from_secs(60)).unwrap();
+        let parallel = engine.threads(4).search(&state, Duration::from_secs(60)).unwrap();
+        assert_eq!(parallel.depth, 3);
+        assert_eq!(parallel.score, single.score);
+        assert!(state.sensible_moves().contains(&parallel.best));
+    }
+
+    #[test]
+    fn test_reports_each_depth() {
+        let state = State::new(Board::generate(2));
+        let mut engine = AlphaBeta::new(FeatureEvaluator::new()).max_depth(3).moves(MoveGeneration::Sensible).budget(Duration::from_secs(60));
+        let mut reported = Vec::new();
+        let result = engine.search_with(&state, Duration::from_secs(60), |r| reported.push((r.depth, r.best.clone()))).unwrap();
+        assert_eq!(reported.iter().map(|(d, _)| *d).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(reported.last().unwrap().1, result.best);
+
+        // As a delegate, the best move of each depth is reported
+        let best = BestMove::default();
+        let m = engine.pick_move_until(&state, state.current_team(), Deadline::after(Duration::from_secs(60)), &best);
+        assert_eq!(m, result.best);
+        assert_eq!(best.take(), Some(m));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fmt, str::FromStr};
+
+use crate::{game::{Ship, State, Team, MAX_SPEED, MIN_SPEED}, util::{Error, Result}};
+
+/// A heuristic that rates a state from the perspective of a team. Higher
+/// scores are better for the team.
+pub trait Evaluator {
+    /// Rates the given state for the given team.
+    fn evaluate(&self, state: &State, team: Team) -> i32;
+}
+
+impl<E> Evaluator for &E where E: Evaluator + ?Sized {
+    fn evaluate(&self, state: &State, team: Team) -> i32 {
+        (**self).evaluate(state, team)
+    }
+}
+
+impl<E> Evaluator for Box<E> where E: Evaluator + ?Sized {
+    fn evaluate(&self, state: &State, team: Team) -> i32 {
+        (**self).evaluate(state, team)
+    }
+}
+
+/// A built-in property of a state that evaluators can weigh. Each feature
+/// is measured as the team's value minus the opponent's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(usize)]
+pub enum Feature {
+    /// The points for the segments and fields the ship advanced, as given by
+    /// `Board::ship_advance_points`.
+    Progress,
+    /// The passengers on board.
+    Passengers,
+    /// The coal left.
+    Coal,
+    /// How close the speed is to the one that covers the remaining distance
+    /// to the goal, as a penalty for being too fast or too slow.
+    SpeedToGoal,
+    /// Whether the team is ahead and thus moves first in the next round.
+    TurnOrder,
+}
+
+impl Feature {
+    /// The total number of features.
+    pub const COUNT: usize = 5;
+
+    /// The features.
+    pub const ALL: [Self; Self::COUNT] = [Self::Progress, Self::Passengers, Self::Coal, Self::SpeedToGoal, Self::TurnOrder];
+
+    /// The feature's index.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Measures the feature for the given team, unweighted.
+    pub fn measure(self, state: &State, team: Team) -> i32 {
+        match self {
+            Self::TurnOrder => if state.determine_ahead_team() == team { 1 } else { -1 },
+            _ => self.measure_ship(state, state.ship(team)) - self.measure_ship(state, state.ship(team.opponent())),
+        }
+    }
+
+    /// Measures the feature for a single ship.
+    fn measure_ship(self, state: &State, ship: Ship) -> i32 {
+        let board = state.board();
+        match self {
+            Self::Progress => board.ship_advance_points(ship).unwrap_or(0),
+            Self::Passengers => ship.passengers as i32,
+            Self::Coal => ship.coal,
+            Self::SpeedToGoal => match board.distance_map().goal().get(ship.position) {
+                Some(distance) => -(ship.speed - (distance as i32).clamp(MIN_SPEED, MAX_SPEED)).abs(),
+                None => 0,
+            },
+            Self::TurnOrder => 0,
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::Progress => "progress",
+            Self::Passengers => "passengers",
+            Self::Coal => "coal",
+            Self::SpeedToGoal => "speed",
+            Self::TurnOrder => "order",
+        })
+    }
+}
+
+impl FromStr for Feature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL.into_iter()
+            .find(|f| f.to_string() == s)
+            .ok_or_else(|| Error::UnknownVariant(format!("Unknown feature {}", s)))
+    }
+}
+
+/// An evaluator that sums up the built-in features, each multiplied by a
+/// weight. The weights can be parsed from a string like
+/// `progress=10,passengers=40`, where omitted features keep their default
+/// weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureEvaluator {
+    weights: [i32; Feature::COUNT],
+}
+
+impl FeatureEvaluator {
+    /// Creates an evaluator with the default weights.
+    pub fn new() -> Self {
+        Self { weights: [10, 60, 4, 3, 5] }
+    }
+
+    /// Sets the weight of the given feature.
+    pub fn weight(mut self, feature: Feature, weight: i32) -> Self {
+        self.weights[feature.index()] = weight;
+        self
+    }
+
+    /// The weight of the given feature.
+    pub fn weight_of(&self, feature: Feature) -> i32 {
+        self.weights[feature.index()]
+    }
+
+    /// Evaluates each feature separately to show what the score is made of.
+    pub fn breakdown(&self, state: &State, team: Team) -> Breakdown {
+        Breakdown {
+            entries: Feature::ALL.map(|feature| {
+                let value = feature.measure(state, team);
+                BreakdownEntry { feature, value, weight: self.weight_of(feature) }
+            }),
+        }
+    }
+}
+
+impl Default for FeatureEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator for FeatureEvaluator {
+    fn evaluate(&self, state: &State, team: Team) -> i32 {
+        Feature::ALL.into_iter()
+            .filter(|&f| self.weight_of(f) != 0)
+            .map(|f| f.measure(state, team) * self.weight_of(f))
+            .sum()
+    }
+}
+
+impl fmt::Display for FeatureEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, feature) in Feature::ALL.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}={}", feature, self.weight_of(feature))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FeatureEvaluator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .try_fold(Self::new(), |evaluator, part| {
+                let (feature, weight) = part.split_once('=')
+                    .ok_or_else(|| Error::Custom(format!("Expected <feature>=<weight>, but got {}", part)))?;
+                Ok(evaluator.weight(feature.trim().parse()?, weight.trim().parse()?))
+            })
+    }
+}
+
+/// The contribution of a single feature to a score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakdownEntry {
+    pub feature: Feature,
+    /// The unweighted value of the feature.
+    pub value: i32,
+    pub weight: i32,
+}
+
+impl BreakdownEntry {
+    /// The weighted value that is added to the score.
+    pub fn contribution(&self) -> i32 {
+        self.value * self.weight
+    }
+}
+
+/// The contributions of all features to a score, as computed by
+/// `FeatureEvaluator::breakdown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakdown {
+    pub entries: [BreakdownEntry; Feature::COUNT],
+}
+
+impl Breakdown {
+    /// The total score.
+    pub fn total(&self) -> i32 {
+        self.entries.iter().map(|e| e.contribution()).sum()
+    }
+}
+
+impl fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{:<10} {:>4} x {:>3} = {:>5}", entry.feature, entry.value, entry.weight, entry.contribution())?;
+        }
+        write!(f, "{:<10} {:>18}", "total", self.total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Board, State, Team};
+
+    use super::{Evaluator, Feature, FeatureEvaluator};
+
+    #[test]
+    fn test_symmetric() {
+        let mut state = State::new(Board::generate(7));
+        let evaluator = FeatureEvaluator::new();
+        for _ in 0..6 {
+            let m = state.simple_moves()[0].clone();
+            state.make(&m).unwrap();
+            for feature in Feature::ALL {
+                assert_eq!(feature.measure(&state, Team::One), -feature.measure(&state, Team::Two), "{} is asymmetric", feature);
+            }
+            assert_eq!(evaluator.evaluate(&state, Team::One), -evaluator.evaluate(&state, Team::Two));
+        }
+    }
+
+    #[test]
+    fn test_weights() {
+        let mut state = State::new(Board::generate(1));
+        state.ship_mut(Team::One).passengers = 2;
+        state.ship_mut(Team::Two).coal = 2;
+        let passengers = FeatureEvaluator::new().weight(Feature::Passengers, 1);
+        let only_passengers = Feature::ALL.into_iter().fold(FeatureEvaluator::new(), |e, f| e.weight(f, 0)).weight(Feature::Passengers, 1);
+        assert_eq!(only_passengers.evaluate(&state, Team::One), 2);
+        assert_eq!(only_passengers.evaluate(&state, Team::Two), -2);
+
+        let breakdown = passengers.breakdown(&state, Team::One);
+        assert_eq!(breakdown.total(), passengers.evaluate(&state, Team::One));
+        assert_eq!(breakdown.entries[Feature::Coal.index()].value, 4);
+        assert!(breakdown.to_string().contains("passengers    2 x   1 =     2"));
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let evaluator: FeatureEvaluator = "passengers=7, coal = -1".parse().unwrap();
+        assert_eq!(evaluator, FeatureEvaluator::new().weight(Feature::Passengers, 7).weight(Feature::Coal, -1));
+        assert_eq!(evaluator.to_string().parse::<FeatureEvaluator>().unwrap(), evaluator);
+        assert_eq!("".parse::<FeatureEvaluator>().unwrap(), FeatureEvaluator::new());
+        assert!("luck=3".parse::<FeatureEvaluator>().is_err());
+        assert!("coal".parse::<FeatureEvaluator>().is_err());
+    }
+}
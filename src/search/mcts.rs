@@ -0,0 +1,659 @@
+use std::{mem, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant}};
+
+use log::{debug, info};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{client::{BestMove, Deadline, GameClientDelegate}, game::{Move, State, Team}};
+
+use super::{Bound, Evaluator, MoveGeneration, TableEntry, TranspositionTable};
+
+/// How moves are picked when playing out a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RolloutPolicy {
+    /// A random move from `State::sensible_moves`.
+    #[default]
+    Random,
+    /// The move with the best evaluation for the moving team among a random
+    /// sample of `GREEDY_SAMPLE` sensible moves.
+    Greedy,
+}
+
+/// The number of moves the greedy rollout policy evaluates. Sensible moves
+/// fall back to all possible moves when the ship is stuck, which are too many
+/// to evaluate in each step.
+pub const GREEDY_SAMPLE: usize = 16;
+
+/// The number of playouts between reports of the most visited move when
+/// searching as a delegate.
+const REPORT_INTERVAL: usize = 256;
+
+/// The statistics of a move at the root of the search tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveStats {
+    pub m: Move,
+    /// The number of playouts through the move.
+    pub visits: u32,
+    /// The average reward of the move for the team making it, between 0 and 1.
+    pub value: f64,
+}
+
+/// A node in the search tree.
+#[derive(Debug, Clone)]
+struct Node {
+    /// The move leading to the node and its index in the moves generated for
+    /// the parent.
+    m: Option<Move>,
+    index: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The moves not expanded yet along with their indices, generated on the
+    /// first visit. They are expanded from the back.
+    untried: Option<Vec<(usize, Move)>>,
+    /// The team that made the move leading to the node.
+    mover: Team,
+    /// The key and turn of the state at the node, for finding it again.
+    key: u64,
+    turn: usize,
+    visits: u32,
+    /// The summed rewards for the mover.
+    reward: f64,
+}
+
+/// A Monte Carlo tree search using UCT to select moves. The tree is kept
+/// between turns and reused if the new state is found in it. With a seed, an
+/// iteration limit instead of a time budget and a single thread, the search
+/// is deterministic.
+pub struct Mcts<E> {
+    evaluator: E,
+    rollout: RolloutPolicy,
+    moves: MoveGeneration,
+    exploration: f64,
+    rollout_depth: usize,
+    /// The evaluation difference that corresponds to a winning chance of
+    /// about 73%, used to score unfinished playouts.
+    scale: f64,
+    budget: Duration,
+    iterations: Option<usize>,
+    threads: usize,
+    rng: StdRng,
+    table: Option<Arc<TranspositionTable>>,
+    nodes: Vec<Node>,
+}
+
+impl<E> Mcts<E> where E: Evaluator + Sync {
+    /// Creates a search that uses the given evaluator to score playouts that
+    /// end before the game does.
+    pub fn new(evaluator: E) -> Self {
+        Self {
+            evaluator,
+            rollout: RolloutPolicy::default(),
+            moves: MoveGeneration::Sensible,
+            exploration: 2f64.sqrt(),
+            rollout_depth: 8,
+            scale: 100.0,
+            budget: Duration::from_secs(1),
+            iterations: None,
+            threads: 1,
+            rng: StdRng::from_entropy(),
+            table: None,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Sets how moves are picked in playouts.
+    pub fn rollout(mut self, rollout: RolloutPolicy) -> Self {
+        self.rollout = rollout;
+        self
+    }
+
+    /// Sets which moves are added to the tree.
+    pub fn moves(mut self, moves: MoveGeneration) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    /// Sets the UCT exploration constant.
+    pub fn exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Sets the number of moves after which a playout is stopped and scored
+    /// by the evaluator.
+    pub fn rollout_depth(mut self, rollout_depth: usize) -> Self {
+        self.rollout_depth = rollout_depth;
+        self
+    }
+
+    /// Sets the evaluation difference that is considered a clear advantage.
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the time to search per move.
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Runs the given number of playouts per move instead of searching for a
+    /// fixed time.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Sets the number of threads to search with. Each additional thread
+    /// grows a tree of its own from the root, seeded from the main random
+    /// number generator, and the statistics of the root moves are merged
+    /// into the main tree afterwards (root parallelization).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Seeds the random number generator.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Sets a transposition table. The best move stored for a position is
+    /// expanded first, and the most visited move at the root is stored after
+    /// each search, so that other engines sharing the table can use it.
+    pub fn table(mut self, table: Arc<TranspositionTable>) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Searches the given state and returns the most visited move, or `None`
+    /// if there are no moves.
+    pub fn search(&mut self, state: &State) -> Option<Move> {
+        self.search_until(state, Instant::now() + self.budget)
+    }
+
+    /// Searches the given state until the deadline, or for the configured
+    /// number of iterations, and returns the most visited move.
+    pub fn search_until(&mut self, state: &State, deadline: Instant) -> Option<Move> {
+        self.search_reporting(state, deadline, None)
+    }
+
+    /// Searches like `search_until`, periodically reporting the most visited
+    /// move to `best` if given.
+    fn search_reporting(&mut self, state: &State, deadline: Instant, best: Option<&BestMove>) -> Option<Move> {
+        self.set_root(state);
+        let iterations = if self.threads > 1 {
+            self.run_parallel(state, deadline, best)
+        } else {
+            self.run(state, deadline, best)
+        };
+        debug!("Ran {} playouts, tree has {} nodes", iterations, self.nodes.len());
+
+        let node = &self.nodes[self.most_visited()?];
+        if let Some(table) = &self.table {
+            // Store the winning chance as an evaluation, undoing the mapping
+            // used for unfinished playouts
+            let value = (node.reward / node.visits.max(1) as f64).clamp(0.001, 0.999);
+            let score = (self.scale * (value / (1.0 - value)).ln()) as i32;
+            table.store(TranspositionTable::key(state), TableEntry { score, bound: Bound::Exact, depth: 0, best: u16::try_from(node.index).ok(), moves: self.moves });
+        }
+        node.m.clone()
+    }
+
+    /// The index of the most visited child of the root, preferring higher
+    /// rewards among equally visited ones.
+    fn most_visited(&self) -> Option<usize> {
+        self.nodes[0].children.iter().copied().max_by(|&a, &b| {
+            let (a, b) = (&self.nodes[a], &self.nodes[b]);
+            a.visits.cmp(&b.visits).then((a.reward / a.visits.max(1) as f64).total_cmp(&(b.reward / b.visits.max(1) as f64)))
+        })
+    }
+
+    /// Runs playouts from the root until the deadline, or for the configured
+    /// number of iterations, and returns the number of playouts. The most
+    /// visited move is reported to `best` every `REPORT_INTERVAL` playouts.
+    fn run(&mut self, state: &State, deadline: Instant, best: Option<&BestMove>) -> usize {
+        let mut state = state.clone();
+        let mut iterations = 0;
+        while self.iterations.map(|n| iterations < n).unwrap_or_else(|| iterations == 0 || Instant::now() < deadline) {
+            self.iterate(&mut state);
+            iterations += 1;
+            if let Some(best) = best.filter(|_| iterations % REPORT_INTERVAL == 0) {
+                if let Some(m) = self.most_visited().and_then(|i| self.nodes[i].m.clone()) {
+                    best.report(m);
+                }
+            }
+        }
+        iterations
+    }
+
+    /// Runs playouts on all threads and merges the root statistics of the
+    /// helper trees into the main tree. Only the main tree's most visited
+    /// move is reported.
+    fn run_parallel(&mut self, state: &State, deadline: Instant, best: Option<&BestMove>) -> usize {
+        let seeds: Vec<u64> = (0..self.threads).map(|_| self.rng.gen()).collect();
+        let nodes = mem::take(&mut self.nodes);
+        let mut main = Mcts { nodes, ..self.worker(&self.evaluator, seeds[0]) };
+        let helpers: Vec<_> = seeds[1..].iter().map(|&seed| self.worker(&self.evaluator, seed)).collect();
+
+        let (mut iterations, helpers) = thread::scope(|scope| {
+            let helpers: Vec<_> = helpers.into_iter()
+                .map(|mut helper| scope.spawn(move || {
+                    helper.set_root(state);
+                    let iterations = helper.run(state, deadline, None);
+                    (iterations, helper)
+                }))
+                .collect();
+            let iterations = main.run(state, deadline, best);
+            (iterations, helpers.into_iter().map(|h| h.join().expect("Helper search panicked")).collect::<Vec<_>>())
+        });
+
+        for (helper_iterations, helper) in helpers {
+            iterations += helper_iterations;
+            main.merge_root(state, &helper);
+        }
+        self.nodes = main.nodes;
+        iterations
+    }
+
+    /// Creates a search with the same settings, the given evaluator and seed
+    /// and an empty tree.
+    fn worker<F>(&self, evaluator: F, seed: u64) -> Mcts<F> {
+        Mcts {
+            evaluator,
+            rollout: self.rollout,
+            moves: self.moves,
+            exploration: self.exploration,
+            rollout_depth: self.rollout_depth,
+            scale: self.scale,
+            budget: self.budget,
+            iterations: self.iterations,
+            threads: 1,
+            rng: StdRng::seed_from_u64(seed),
+            table: self.table.clone(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Adds the statistics of the root moves in the other tree, which has to
+    /// be rooted at the same state, to this one. Moves that weren't expanded
+    /// here yet are added as leaves.
+    fn merge_root<F>(&mut self, state: &State, other: &Mcts<F>) {
+        let Some(other_root) = other.nodes.first() else { return };
+        if self.nodes[0].untried.is_none() {
+            self.nodes[0].untried = Some(self.untried_moves(state));
+        }
+        self.nodes[0].visits += other_root.visits;
+        self.nodes[0].reward += other_root.reward;
+        for &c in &other_root.children {
+            let theirs = &other.nodes[c];
+            match self.nodes[0].children.iter().copied().find(|&i| self.nodes[i].index == theirs.index) {
+                Some(ours) => {
+                    self.nodes[ours].visits += theirs.visits;
+                    self.nodes[ours].reward += theirs.reward;
+                },
+                None => {
+                    self.nodes[0].untried.as_mut().unwrap().retain(|&(i, _)| i != theirs.index);
+                    let child = self.nodes.len();
+                    self.nodes.push(Node { parent: Some(0), children: Vec::new(), untried: None, ..theirs.clone() });
+                    self.nodes[0].children.push(child);
+                },
+            }
+        }
+    }
+
+    /// Grows the tree for the given state until `stop` is set, so that a
+    /// later search from one of its successors can reuse the work.
+    pub fn ponder(&mut self, state: &State, stop: &AtomicBool) {
+        self.set_root(state);
+        let mut state = state.clone();
+        let mut iterations = 0;
+        while !stop.load(Ordering::Relaxed) && !state.is_over() {
+            self.iterate(&mut state);
+            iterations += 1;
+        }
+        debug!("Pondered for {} playouts, tree has {} nodes", iterations, self.nodes.len());
+    }
+
+    /// The statistics of the moves at the root.
+    pub fn statistics(&self) -> Vec<MoveStats> {
+        self.nodes.first().map(|root| root.children.iter()
+            .map(|&c| {
+                let node = &self.nodes[c];
+                MoveStats { m: node.m.clone().unwrap(), visits: node.visits, value: node.reward / node.visits.max(1) as f64 }
+            })
+            .collect())
+            .unwrap_or_default()
+    }
+
+    /// The number of nodes in the tree.
+    pub fn tree_size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Makes the node for the given state the root, keeping its subtree if it
+    /// is found within the next few moves of the old root.
+    fn set_root(&mut self, state: &State) {
+        let found = (!self.nodes.is_empty()).then(|| {
+            let mut layer = vec![0];
+            for _ in 0..=3 {
+                if let Some(&i) = layer.iter().find(|&&i| self.nodes[i].key == state.key() && self.nodes[i].turn == state.turn()) {
+                    return Some(i);
+                }
+                layer = layer.iter().flat_map(|&i| self.nodes[i].children.iter().copied()).collect();
+            }
+            None
+        }).flatten();
+
+        match found {
+            Some(index) => {
+                let mut nodes = Vec::new();
+                self.copy_subtree(index, None, &mut nodes);
+                debug!("Reusing {} of {} nodes", nodes.len(), self.nodes.len());
+                self.nodes = nodes;
+            },
+            None => {
+                self.nodes = vec![Node {
+                    m: None,
+                    index: 0,
+                    parent: None,
+                    children: Vec::new(),
+                    untried: None,
+                    mover: state.other_team(),
+                    key: state.key(),
+                    turn: state.turn(),
+                    visits: 0,
+                    reward: 0.0,
+                }];
+            },
+        }
+    }
+
+    /// Copies the subtree at the given index into the given nodes.
+    fn copy_subtree(&self, index: usize, parent: Option<usize>, nodes: &mut Vec<Node>) -> usize {
+        let new_index = nodes.len();
+        nodes.push(Node { parent, children: Vec::new(), ..self.nodes[index].clone() });
+        for &child in &self.nodes[index].children {
+            let new_child = self.copy_subtree(child, Some(new_index), nodes);
+            nodes[new_index].children.push(new_child);
+        }
+        new_index
+    }
+
+    /// Runs one playout: selects a leaf, expands it, plays out the position
+    /// and propagates the result back up. The state is restored afterwards.
+    fn iterate(&mut self, state: &mut State) {
+        let mut undos = Vec::new();
+        let mut index = 0;
+
+        // Select
+        loop {
+            if state.is_over() {
+                break;
+            }
+            if self.nodes[index].untried.is_none() {
+                self.nodes[index].untried = Some(self.untried_moves(state));
+            }
+            if !self.nodes[index].untried.as_ref().unwrap().is_empty() {
+                break;
+            }
+            let Some(child) = self.select_child(index) else { break };
+            undos.push(state.make(self.nodes[child].m.as_ref().unwrap()).expect("Tree moves should be valid"));
+            index = child;
+        }
+
+        // Expand
+        if !state.is_over() {
+            while let Some((move_index, m)) = self.nodes[index].untried.as_mut().unwrap().pop() {
+                let mover = state.current_team();
+                let Ok(undo) = state.make(&m) else { continue };
+                undos.push(undo);
+                let child = self.nodes.len();
+                self.nodes.push(Node {
+                    m: Some(m),
+                    index: move_index,
+                    parent: Some(index),
+                    children: Vec::new(),
+                    untried: None,
+                    mover,
+                    key: state.key(),
+                    turn: state.turn(),
+                    visits: 0,
+                    reward: 0.0,
+                });
+                self.nodes[index].children.push(child);
+                index = child;
+                break;
+            }
+        }
+
+        // Play out
+        let reward = self.playout(state);
+
+        // Propagate
+        let mut current = Some(index);
+        while let Some(i) = current {
+            let node = &mut self.nodes[i];
+            node.visits += 1;
+            node.reward += if node.mover == Team::One { reward } else { 1.0 - reward };
+            current = node.parent;
+        }
+
+        for undo in undos.into_iter().rev() {
+            state.unmake(undo);
+        }
+    }
+
+    /// Generates the moves to expand at the given state, with the best move
+    /// from the table last so that it is expanded first.
+    fn untried_moves(&self, state: &State) -> Vec<(usize, Move)> {
+        let mut moves: Vec<_> = self.moves.moves(state).into_iter().enumerate().collect();
+        let best = self.table.as_ref()
+            .and_then(|t| t.probe(TranspositionTable::key(state)))
+            .and_then(|e| e.best_for(self.moves))
+            .filter(|&b| b < moves.len());
+        if let Some(best) = best {
+            moves[best..].rotate_left(1);
+        }
+        moves
+    }
+
+    /// Picks the child with the highest upper confidence bound.
+    fn select_child(&self, index: usize) -> Option<usize> {
+        let log_visits = (self.nodes[index].visits.max(1) as f64).ln();
+        self.nodes[index].children.iter().copied().max_by(|&a, &b| {
+            let uct = |i: usize| {
+                let node = &self.nodes[i];
+                let visits = node.visits.max(1) as f64;
+                node.reward / visits + self.exploration * (log_visits / visits).sqrt()
+            };
+            uct(a).total_cmp(&uct(b))
+        })
+    }
+
+    /// Plays out the position and returns the reward for team one. The state
+    /// is restored afterwards.
+    fn playout(&mut self, state: &mut State) -> f64 {
+        let mut undos = Vec::new();
+        for _ in 0..self.rollout_depth {
+            if state.is_over() {
+                break;
+            }
+            let Some(m) = self.rollout_move(state) else { break };
+            match state.make(&m) {
+                Ok(undo) => undos.push(undo),
+                Err(_) => break,
+            }
+        }
+
+        let reward = if state.is_over() {
+            match state.result().winner() {
+                Some(winner) if winner.team() == Team::One => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            }
+        } else {
+            let score = self.evaluator.evaluate(state, Team::One) as f64;
+            1.0 / (1.0 + (-score / self.scale).exp())
+        };
+
+        for undo in undos.into_iter().rev() {
+            state.unmake(undo);
+        }
+        reward
+    }
+
+    /// Picks a move to play out with.
+    fn rollout_move(&mut self, state: &mut State) -> Option<Move> {
+        let mut moves = state.sensible_moves();
+        match self.rollout {
+            RolloutPolicy::Random => moves.choose(&mut self.rng).cloned(),
+            RolloutPolicy::Greedy => {
+                let team = state.current_team();
+                let (sample, _) = moves.partial_shuffle(&mut self.rng, GREEDY_SAMPLE);
+                sample.iter()
+                    .filter_map(|m| {
+                        let undo = state.make(m).ok()?;
+                        let score = self.evaluator.evaluate(state, team);
+                        state.unmake(undo);
+                        Some((score, m))
+                    })
+                    .max_by_key(|(score, _)| *score)
+                    .map(|(_, m)| m.clone())
+            },
+        }
+    }
+}
+
+impl<E> GameClientDelegate for Mcts<E> where E: Evaluator + Sync {
+    fn pick_move(&mut self, state: &State, _my_team: Team) -> Move {
+        let m = self.search(state).expect("No move found!");
+        info!("Chose move {} after {} playouts", m, self.nodes[0].visits);
+        m
+    }
+
+    fn pick_move_until(&mut self, state: &State, _my_team: Team, deadline: Deadline, best: &BestMove) -> Move {
+        let m = self.search_reporting(state, deadline.instant().min(Instant::now() + self.budget), Some(best)).expect("No move found!");
+        info!("Chose move {} after {} playouts in {:?}", m, self.nodes[0].visits, deadline.elapsed());
+        m
+    }
+
+    fn ponder(&mut self, state: &State, _my_team: Team, stop: &AtomicBool) {
+        Mcts::ponder(self, state, stop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::Duration};
+
+    use crate::{client::{BestMove, Deadline, GameClientDelegate}, game::{Board, State}, search::{FeatureEvaluator, MoveGeneration, TranspositionTable}};
+
+    use super::{Mcts, RolloutPolicy, REPORT_INTERVAL};
+
+    #[test]
+    fn test_seeded_is_deterministic() {
+        let state = State::new(Board::generate(3));
+        for rollout in [RolloutPolicy::Random, RolloutPolicy::Greedy] {
+            let search = || Mcts::new(FeatureEvaluator::new()).rollout(rollout).rollout_depth(4).iterations(60).seed(42);
+            let (mut a, mut b) = (search(), search());
+            assert_eq!(a.search(&state), b.search(&state));
+            assert_eq!(a.statistics(), b.statistics());
+            assert_eq!(a.statistics().iter().map(|s| s.visits).sum::<u32>(), 60);
+        }
+    }
+
+    #[test]
+    fn test_shares_table() {
+        let state = State::new(Board::generate(8));
+        let table = Arc::new(TranspositionTable::new(1024));
+        let mut mcts = Mcts::new(FeatureEvaluator::new()).rollout_depth(4).iterations(50).seed(3).table(table.clone());
+        let m = mcts.search(&state).unwrap();
+        let entry = table.probe(TranspositionTable::key(&state)).unwrap();
+        assert_eq!(state.sensible_moves()[entry.best_for(MoveGeneration::Sensible).unwrap()], m);
+
+        // Another search expands the stored move first
+        let mut other = Mcts::new(FeatureEvaluator::new()).iterations(1).seed(4).table(table);
+        other.search(&state);
+        assert_eq!(other.statistics()[0].m, m);
+    }
+
+    #[test]
+    fn test_reuses_tree() {
+        let mut state = State::new(Board::generate(5));
+        let mut mcts = Mcts::new(FeatureEvaluator::new()).rollout_depth(4).iterations(100).seed(1);
+        let team = state.current_team();
+        let m = mcts.pick_move(&state, team);
+        assert!(state.sensible_moves().contains(&m));
+
+        // Play our move and the opponent's most visited reply, then search
+        // again from the resulting state
+        state.make(&m).unwrap();
+        let reply = mcts.nodes.iter()
+            .filter(|n| n.parent.is_some_and(|p| mcts.nodes[p].parent == Some(0) && mcts.nodes[p].m.as_ref() == Some(&m)))
+            .max_by_key(|n| n.visits)
+            .map(|n| n.m.clone().unwrap())
+            .unwrap();
+        state.make(&reply).unwrap();
+        let expected = mcts.nodes.iter().find(|n| n.m.as_ref() == Some(&reply) && n.key == state.key()).unwrap().visits;
+        assert!(expected > 0);
+
+        mcts.set_root(&state);
+        assert_eq!(mcts.nodes[0].visits, expected);
+        assert!(mcts.tree_size() > 1);
+        let next = mcts.pick_move(&state, state.current_team());
+        assert!(state.sensible_moves().contains(&next));
+    }
+
+    #[test]
+    fn test_reports_most_visited() {
+        let state = State::new(Board::generate(6));
+        let mut mcts = Mcts::new(FeatureEvaluator::new()).rollout_depth(4).iterations(2 * REPORT_INTERVAL).seed(2);
+        let best = BestMove::default();
+        let m = mcts.pick_move_until(&state, state.current_team(), Deadline::after(Duration::from_secs(60)), &best);
+
+        // The last report happens after the final playout
+        assert_eq!(best.take(), Some(m));
+        assert!(mcts.search(&state).is_some());
+        assert_eq!(best.take(), None);
+    }
+
+    #[test]
+    fn test_ponder_is_reused() {
+        let mut state = State::new(Board::generate(6));
+        let mut mcts = Mcts::new(FeatureEvaluator::new()).rollout_depth(4).seed(2);
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                stop.store(true, Ordering::Relaxed);
+            });
+            mcts.ponder(&state, &stop);
+        });
+        assert!(mcts.nodes[0].visits > 0);
+
+        // The opponent's reply was explored while pondering
+        let reply = mcts.statistics().into_iter().max_by_key(|s| s.visits).unwrap();
+        state.make(&reply.m).unwrap();
+        mcts.set_root(&state);
+        assert_eq!(mcts.nodes[0].visits, reply.visits);
+    }
+
+    #[test]
+    fn test_parallel_merges_statistics() {
+        let state = State::new(Board::generate(4));
+        let mut mcts = Mcts::new(FeatureEvaluator::new()).rollout_depth(4).iterations(40).threads(3).seed(5);
+        let m = mcts.search(&state).unwrap();
+        let statistics = mcts.statistics();
+        assert_eq!(statistics.iter().map(|s| s.visits).sum::<u32>(), 120);
+        assert_eq!(mcts.nodes[0].visits, 120);
+        assert_eq!(statistics.iter().max_by_key(|s| s.visits).unwrap().m, m);
+
+        // Each move appears once
+        let mut indices: Vec<_> = mcts.nodes[0].children.iter().map(|&c| mcts.nodes[c].index).collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), statistics.len());
+    }
+}
@@ -0,0 +1,9 @@
+mod alpha_beta;
+mod evaluator;
+mod mcts;
+mod transposition;
+
+pub use alpha_beta::*;
+pub use evaluator::*;
+pub use mcts::*;
+pub use transposition::*;
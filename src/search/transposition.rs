@@ -0,0 +1,305 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::game::State;
+
+use super::{MoveGeneration, WIN_SCORE};
+
+/// How a stored score relates to the true score of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The true score is at least the stored one.
+    Lower,
+    /// The true score is at most the stored one.
+    Upper,
+}
+
+/// The information stored for a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TableEntry {
+    /// The score from the perspective of the team to move.
+    pub score: i32,
+    pub bound: Bound,
+    /// The depth the position was searched to.
+    pub depth: u8,
+    /// The index of the best move in the moves generated for the position.
+    /// Indices above `MAX_BEST_INDEX` aren't stored.
+    pub best: Option<u16>,
+    /// The move generation the index of the best move refers to.
+    pub moves: MoveGeneration,
+}
+
+impl TableEntry {
+    /// The largest index of a best move that fits into an entry.
+    pub const MAX_BEST_INDEX: u16 = (1 << 14) - 2;
+
+    /// The index of the best move among the moves from the given move
+    /// generation, if it was stored for the same generation.
+    pub fn best_for(&self, moves: MoveGeneration) -> Option<usize> {
+        self.best.filter(|_| self.moves == moves).map(|b| b as usize)
+    }
+
+    /// Packs the entry and the generation into a single word.
+    fn pack(self, generation: u8) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let moves = match self.moves {
+            MoveGeneration::Canonical => 0,
+            MoveGeneration::Sensible => 1,
+        };
+        let best = self.best.filter(|&b| b <= Self::MAX_BEST_INDEX).map(|b| b as u64 + 1).unwrap_or(0);
+        (self.score as u32 as u64)
+            | (self.depth as u64) << 32
+            | bound << 40
+            | moves << 42
+            | best << 44
+            | ((generation & GENERATION_MASK) as u64) << 58
+    }
+
+    /// Unpacks an entry, returning `None` for empty slots.
+    fn unpack(data: u64) -> Option<Self> {
+        let bound = match (data >> 40) & 0b11 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        let moves = match (data >> 42) & 0b11 {
+            0 => MoveGeneration::Canonical,
+            _ => MoveGeneration::Sensible,
+        };
+        let best = ((data >> 44) & 0x3FFF) as u16;
+        Some(Self {
+            score: data as u32 as i32,
+            bound,
+            depth: (data >> 32) as u8,
+            best: best.checked_sub(1),
+            moves,
+        })
+    }
+
+    /// The generation of packed data.
+    fn generation(data: u64) -> u8 {
+        (data >> 58) as u8
+    }
+}
+
+/// The generations wrap around after 64 searches.
+const GENERATION_MASK: u8 = 0b11_1111;
+
+/// Scores this close to `WIN_SCORE` are wins or losses in some number of
+/// moves, which are stored relative to the position instead of the root.
+const WIN_THRESHOLD: i32 = WIN_SCORE - 1000;
+
+/// A slot storing the key XOR the data next to the data, so that torn writes
+/// from concurrent stores are detected as a key mismatch.
+#[derive(Debug, Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A fixed-size hash table of search results that replaces entries by depth.
+/// It can be shared between threads and search engines, e.g. in an `Arc`.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table with room for at least the given number of entries,
+    /// rounded up to a power of two.
+    pub fn new(entries: usize) -> Self {
+        let slots = (0..entries.max(1).next_power_of_two()).map(|_| Slot::default()).collect();
+        Self { slots, generation: AtomicU8::new(0) }
+    }
+
+    /// Creates a table that takes up about the given number of megabytes.
+    pub fn with_megabytes(megabytes: usize) -> Self {
+        let entries = megabytes * 1024 * 1024 / std::mem::size_of::<Slot>();
+        Self::new((entries / 2 + 1).next_power_of_two())
+    }
+
+    /// The number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The key of the given state in the table. Unlike `State::key`, it covers
+    /// the turn, since the round limit and the end-of-round checks make the
+    /// same position score differently at different turns.
+    pub fn key(state: &State) -> u64 {
+        state.key() ^ (state.turn() as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    /// Marks the start of a new search, after which entries from earlier
+    /// searches are replaced first.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Looks up the entry for the given key.
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+        if check ^ data == key { TableEntry::unpack(data) } else { None }
+    }
+
+    /// Stores an entry for the given key, unless the slot holds an entry from
+    /// the current search that was searched deeper.
+    pub fn store(&self, key: u64, entry: TableEntry) {
+        let generation = self.generation.load(Ordering::Relaxed) & GENERATION_MASK;
+        let slot = self.slot(key);
+        let old = slot.data.load(Ordering::Relaxed);
+        let replace = match TableEntry::unpack(old) {
+            Some(existing) => TableEntry::generation(old) != generation || entry.depth >= existing.depth,
+            None => true,
+        };
+        if replace {
+            let data = entry.pack(generation);
+            slot.data.store(data, Ordering::Relaxed);
+            slot.check.store(key ^ data, Ordering::Relaxed);
+        }
+    }
+
+    /// The slot for the given key.
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+}
+
+/// Converts a score relative to the root into one relative to the position
+/// at the given ply, for storing in the table.
+pub(crate) fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= WIN_THRESHOLD {
+        score + ply as i32
+    } else if score <= -WIN_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Converts a score from the table back into one relative to the root.
+pub(crate) fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= WIN_THRESHOLD {
+        score - ply as i32
+    } else if score <= -WIN_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use crate::{game::{Board, State}, search::MoveGeneration};
+
+    use super::{Bound, TableEntry, TranspositionTable};
+
+    #[test]
+    fn test_pack_roundtrip() {
+        for entry in [
+            TableEntry { score: -123_456, bound: Bound::Upper, depth: 255, best: Some(0), moves: MoveGeneration::Sensible },
+            TableEntry { score: i32::MAX, bound: Bound::Exact, depth: 0, best: None, moves: MoveGeneration::Canonical },
+            TableEntry { score: i32::MIN, bound: Bound::Lower, depth: 7, best: Some(TableEntry::MAX_BEST_INDEX), moves: MoveGeneration::Sensible },
+        ] {
+            assert_eq!(TableEntry::unpack(entry.pack(63)), Some(entry));
+        }
+        assert_eq!(TableEntry::unpack(0), None);
+
+        // Larger indices are dropped rather than overflowing into the
+        // generation or pointing to another move
+        let entry = TableEntry { score: -1, bound: Bound::Exact, depth: 3, best: Some(u16::MAX), moves: MoveGeneration::Canonical };
+        let data = entry.pack(5);
+        assert_eq!(TableEntry::generation(data), 5);
+        assert_eq!(TableEntry::unpack(data), Some(TableEntry { best: None, ..entry }));
+    }
+
+    #[test]
+    fn test_best_is_per_move_generation() {
+        let state = State::new(Board::generate(2));
+        let canonical = MoveGeneration::Canonical.moves(&state);
+        let sensible = MoveGeneration::Sensible.moves(&state);
+        let index = sensible.len() - 1;
+        let entry = TableEntry { score: 0, bound: Bound::Exact, depth: 1, best: Some(index as u16), moves: MoveGeneration::Sensible };
+        assert_eq!(entry.best_for(MoveGeneration::Sensible), Some(index));
+        assert_ne!(canonical.get(index), Some(&sensible[index]), "The index should mean another move to the other generation");
+        assert_eq!(entry.best_for(MoveGeneration::Canonical), None);
+
+        // The move generation is deterministic, so the index stays valid
+        assert_eq!(MoveGeneration::Sensible.moves(&state)[index], sensible[index]);
+    }
+
+    #[test]
+    fn test_replaces_by_depth() {
+        let table = TranspositionTable::new(1000);
+        assert_eq!(table.capacity(), 1024);
+        let entry = |depth| TableEntry { score: depth as i32, bound: Bound::Exact, depth, best: None, moves: MoveGeneration::Canonical };
+        let key = 42;
+        let colliding = key + table.capacity() as u64;
+
+        assert_eq!(table.probe(key), None);
+        table.store(key, entry(3));
+        assert_eq!(table.probe(key), Some(entry(3)));
+        table.store(colliding, entry(2));
+        assert_eq!(table.probe(key), Some(entry(3)));
+        assert_eq!(table.probe(colliding), None);
+        table.store(colliding, entry(3));
+        assert_eq!(table.probe(colliding), Some(entry(3)));
+
+        // Entries from earlier searches are always replaced
+        table.new_search();
+        table.store(key, entry(1));
+        assert_eq!(table.probe(key), Some(entry(1)));
+        table.clear();
+        assert_eq!(table.probe(key), None);
+    }
+
+    #[test]
+    fn test_key_covers_turn() {
+        let mut state = State::new(Board::generate(0));
+        let key = TranspositionTable::key(&state);
+        let m = state.sensible_moves()[0].clone();
+        let undo = state.make(&m).unwrap();
+        assert_ne!(TranspositionTable::key(&state), key);
+        state.unmake(undo);
+        assert_eq!(TranspositionTable::key(&state), key);
+    }
+
+    #[test]
+    fn test_concurrent_stores() {
+        let table = Arc::new(TranspositionTable::new(64));
+        let threads: Vec<_> = (0..4u64).map(|t| {
+            let table = table.clone();
+            thread::spawn(move || {
+                for i in 0..10_000u64 {
+                    let key = i.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ t;
+                    table.store(key, TableEntry { score: (key % 1000) as i32, bound: Bound::Exact, depth: 1, best: None, moves: MoveGeneration::Canonical });
+                    if let Some(entry) = table.probe(key) {
+                        assert_eq!(entry.score, (key % 1000) as i32);
+                    }
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod record;
+mod runner;
+mod sprt;
+
+pub use record::*;
+pub use runner::*;
+pub use sprt::*;
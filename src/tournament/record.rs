@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::referee::Forfeit;
+
+/// The two-sided 95% quantile of the standard normal distribution.
+const Z_95: f64 = 1.959_964;
+
+/// The results of an entrant against a single opponent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// The points collected by the entrant's ship, summed over all games.
+    pub points: i64,
+    /// The games lost by an invalid move, by the kind of mistake.
+    pub violations: BTreeMap<&'static str, u32>,
+    /// The games lost by taking too long to move.
+    pub timeouts: u32,
+}
+
+impl Record {
+    /// The number of games played.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The average score per game, counting wins as 1 and draws as 1/2.
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            games => (self.wins as f64 + self.draws as f64 / 2.0) / games as f64,
+        }
+    }
+
+    /// The average points per game.
+    pub fn average_points(&self) -> f64 {
+        self.points as f64 / self.games().max(1) as f64
+    }
+
+    /// Records a game the entrant forfeited.
+    pub fn add_forfeit(&mut self, forfeit: Forfeit) {
+        match forfeit {
+            Forfeit::Violation(mistake) => *self.violations.entry(mistake.kind()).or_default() += 1,
+            Forfeit::Timeout => self.timeouts += 1,
+        }
+    }
+
+    /// The variance of the score of a single game.
+    pub fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games().max(1) as f64;
+        (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2)) / games
+    }
+
+    /// The score and variance with half a win and half a loss added, so
+    /// that one-sided results don't have a variance of zero.
+    pub(crate) fn regularized(&self) -> (f64, f64) {
+        let played = self.games() as f64;
+        let games = played + 1.0;
+        let score = (self.score() * played + 0.5) / games;
+        let variance = (played * (self.variance() + (self.score() - score).powi(2))
+            + 0.5 * (1.0 - score).powi(2) + 0.5 * score.powi(2)) / games;
+        (score, variance)
+    }
+
+    /// The Elo difference to the opponent, or `None` if no games were played.
+    pub fn elo(&self) -> Option<Elo> {
+        let games = self.games();
+        if games == 0 {
+            return None;
+        }
+        let score = self.score();
+        let (_, variance) = self.regularized();
+        let margin = Z_95 * (variance / games as f64).sqrt();
+        Some(Elo {
+            difference: Elo::from_score(score),
+            lower: Elo::from_score((score - margin).max(0.0)),
+            upper: Elo::from_score((score + margin).min(1.0)),
+        })
+    }
+}
+
+/// An Elo difference estimated from a score, along with the bounds of its
+/// 95% confidence interval. Scores of 0 or 1 give infinite differences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elo {
+    pub difference: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Elo {
+    /// The Elo difference at which the given score is expected.
+    pub fn from_score(score: f64) -> f64 {
+        -400.0 * (1.0 / score - 1.0).log10()
+    }
+
+    /// The score expected at the given Elo difference.
+    pub fn expected_score(difference: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf(-difference / 400.0))
+    }
+}
+
+impl fmt::Display for Elo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:+.1} [{:+.1}, {:+.1}]", self.difference, self.lower, self.upper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::MoveMistake, referee::Forfeit};
+
+    use super::{Elo, Record};
+
+    #[test]
+    fn test_score_and_elo() {
+        let record = Record { wins: 6, draws: 0, losses: 2, ..Default::default() };
+        assert_eq!(record.games(), 8);
+        assert_eq!(record.score(), 0.75);
+        assert!((record.variance() - 0.1875).abs() < 1e-9);
+
+        let elo = record.elo().unwrap();
+        assert!((elo.difference - 190.85).abs() < 0.01);
+        assert!(elo.lower < elo.difference && elo.difference < elo.upper);
+        assert!((Elo::expected_score(elo.difference) - 0.75).abs() < 1e-9);
+
+        assert_eq!(Record::default().elo(), None);
+        let even = Record { wins: 3, draws: 2, losses: 3, ..Default::default() }.elo().unwrap();
+        assert_eq!(even.difference, 0.0);
+        assert_eq!(even.lower, -even.upper);
+
+        // Winning every game only bounds the difference from below
+        let sweep = Record { wins: 6, ..Default::default() }.elo().unwrap();
+        assert!(sweep.lower.is_finite() && sweep.lower > 0.0);
+        assert_eq!(sweep.upper, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_forfeits() {
+        let mut record = Record::default();
+        record.add_forfeit(Forfeit::Violation(MoveMistake::MovementPointsLeft(2)));
+        record.add_forfeit(Forfeit::Violation(MoveMistake::MovementPointsLeft(1)));
+        record.add_forfeit(Forfeit::Timeout);
+        assert_eq!(record.violations.into_iter().collect::<Vec<_>>(), [("MovementPointsLeft", 2)]);
+        assert_eq!(record.timeouts, 1);
+    }
+}
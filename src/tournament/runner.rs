@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use log::info;
+
+use crate::{client::{GameClientDelegate, MOVE_TIME}, game::{Board, Rules, State, Team}, protocol::GameResult, referee::{Forfeit, Referee}};
+
+use super::Record;
+
+/// Creates a fresh delegate for each game.
+pub type Factory = Box<dyn Fn() -> Box<dyn GameClientDelegate>>;
+
+/// A strategy taking part in a tournament.
+pub struct Entrant {
+    pub name: String,
+    factory: Factory,
+}
+
+/// The results of two entrants against each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pairing {
+    /// The indices of the entrants.
+    pub entrants: [usize; 2],
+    /// The record of each entrant against the other.
+    pub records: [Record; 2],
+}
+
+/// A finished tournament game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameReport {
+    /// The seed of the generated board.
+    pub seed: u64,
+    /// The indices of the entrants playing team one and two.
+    pub entrants: [usize; 2],
+    pub result: GameResult,
+    pub forfeit: Option<(Team, Forfeit)>,
+}
+
+/// Plays every pair of entrants against each other in-process on a series
+/// of generated boards. Each board is played twice, so that both entrants
+/// start once.
+pub struct Tournament {
+    entrants: Vec<Entrant>,
+    boards: u64,
+    seed: u64,
+    rules: Rules,
+    move_time: Option<Duration>,
+}
+
+impl Tournament {
+    /// Creates a tournament without entrants on 10 boards, limiting moves
+    /// to the server's move time.
+    pub fn new() -> Self {
+        Self { entrants: Vec::new(), boards: 10, seed: 0, rules: Rules::default(), move_time: Some(MOVE_TIME) }
+    }
+
+    /// Adds an entrant that plays with delegates from the given factory.
+    pub fn entrant(mut self, name: impl Into<String>, factory: impl Fn() -> Box<dyn GameClientDelegate> + 'static) -> Self {
+        self.entrants.push(Entrant { name: name.into(), factory: Box::new(factory) });
+        self
+    }
+
+    /// Sets the number of boards each pair of entrants plays on.
+    pub fn boards(mut self, boards: u64) -> Self {
+        self.boards = boards;
+        self
+    }
+
+    /// Sets the seed of the first board. The boards use consecutive seeds.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the rules the boards are generated with.
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Sets the time limit per move, or removes it.
+    pub fn move_time(mut self, move_time: Option<Duration>) -> Self {
+        self.move_time = move_time;
+        self
+    }
+
+    /// The entrants in the order they were added.
+    pub fn entrants(&self) -> &[Entrant] {
+        &self.entrants
+    }
+
+    /// Plays all games and returns the results of each pair of entrants,
+    /// invoking the callback after each game.
+    pub fn run(&self, mut on_game: impl FnMut(&GameReport)) -> Vec<Pairing> {
+        let mut pairings: Vec<Pairing> = (0..self.entrants.len())
+            .flat_map(|a| (a + 1..self.entrants.len()).map(move |b| Pairing { entrants: [a, b], records: Default::default() }))
+            .collect();
+
+        for seed in self.seed..self.seed + self.boards {
+            for pairing in &mut pairings {
+                let [a, b] = pairing.entrants;
+                for entrants in [[a, b], [b, a]] {
+                    let report = self.play(seed, entrants);
+                    pairing.add(&report);
+                    on_game(&report);
+                }
+            }
+        }
+        pairings
+    }
+
+    /// Plays a single game between the given entrants.
+    fn play(&self, seed: u64, entrants: [usize; 2]) -> GameReport {
+        let [one, two] = entrants.map(|e| (self.entrants[e].factory)());
+        let mut referee = Referee::new(one, two);
+        if let Some(move_time) = self.move_time {
+            referee = referee.move_time(move_time);
+        }
+        let result = referee.play(State::new(Board::generate_with_rules(seed, self.rules)));
+        info!("Board {}: {} vs {}, winner {:?}", seed, self.entrants[entrants[0]].name, self.entrants[entrants[1]].name, result.winner());
+        GameReport { seed, entrants, result, forfeit: referee.forfeit() }
+    }
+}
+
+impl Default for Tournament {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pairing {
+    /// Adds a game between the entrants to their records.
+    fn add(&mut self, report: &GameReport) {
+        let swapped = report.entrants[0] != self.entrants[0];
+        for (i, team) in Team::ALL.into_iter().enumerate() {
+            let record = &mut self.records[if swapped { 1 - i } else { i }];
+            match report.result.winner() {
+                Some(winner) if winner.team() == team => record.wins += 1,
+                Some(_) => record.losses += 1,
+                None => record.draws += 1,
+            }
+            record.points += State::result_points(&report.result, team).unwrap_or(0) as i64;
+            if let Some((_, forfeit)) = report.forfeit.filter(|&(t, _)| t == team) {
+                record.add_forfeit(forfeit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::referee::delegates::{EmptyMove, FirstMove};
+
+    use super::Tournament;
+
+    #[test]
+    fn test_plays_both_sides() {
+        let tournament = Tournament::new()
+            .entrant("first", || Box::new(FirstMove))
+            .entrant("empty", || Box::new(EmptyMove))
+            .entrant("other", || Box::new(FirstMove))
+            .boards(2)
+            .seed(5)
+            .move_time(Some(Duration::from_secs(10)));
+        let mut games = Vec::new();
+        let pairings = tournament.run(|report| games.push((report.seed, report.entrants)));
+        assert_eq!(games.len(), 12);
+        assert_eq!(&games[..4], [(5, [0, 1]), (5, [1, 0]), (5, [0, 2]), (5, [2, 0])]);
+        assert_eq!(pairings.iter().map(|p| p.entrants).collect::<Vec<_>>(), [[0, 1], [0, 2], [1, 2]]);
+
+        let [first, empty] = &pairings[0].records;
+        assert_eq!((first.wins, first.draws, first.losses), (4, 0, 0));
+        assert_eq!((empty.wins, empty.draws, empty.losses), (0, 0, 4));
+        assert_eq!(empty.violations.get("NoActions"), Some(&4));
+        assert!(first.violations.is_empty());
+
+        // The records mirror each other
+        let [first, other] = &pairings[1].records;
+        assert_eq!(first.games(), 4);
+        assert_eq!((first.wins, first.draws, first.losses), (other.losses, other.draws, other.wins));
+        assert!(first.points > 0 && other.points > 0);
+    }
+}
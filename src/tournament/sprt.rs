@@ -0,0 +1,123 @@
+use std::fmt;
+
+use super::{Elo, Record};
+
+/// A sequential probability ratio test of whether an entrant is stronger
+/// than its opponent, deciding between an Elo difference of `elo0` and one
+/// of `elo1` with the given error probabilities. The log-likelihood ratio is
+/// approximated from the mean and variance of the game scores.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// The probability of accepting `elo1` although `elo0` holds.
+    pub alpha: f64,
+    /// The probability of accepting `elo0` although `elo1` holds.
+    pub beta: f64,
+}
+
+/// The outcome of a sequential probability ratio test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SprtVerdict {
+    /// The entrant is stronger by `elo1`.
+    Stronger,
+    /// The entrant is not stronger than by `elo0`.
+    NotStronger,
+    /// More games are needed.
+    Undecided,
+}
+
+impl Sprt {
+    /// Creates a test between the given Elo differences with error
+    /// probabilities of 5%.
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// The log-likelihood ratio of `elo1` over `elo0` given the record, with
+    /// half a win and half a loss added to it.
+    pub fn llr(&self, record: &Record) -> f64 {
+        if record.games() == 0 {
+            return 0.0;
+        }
+        let games = record.games() as f64 + 1.0;
+        let (score, variance) = record.regularized();
+        let (s0, s1) = (Elo::expected_score(self.elo0), Elo::expected_score(self.elo1));
+        games * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    /// The log-likelihood ratios below and above which `elo0` and `elo1`
+    /// are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Decides the test given the record.
+    pub fn verdict(&self, record: &Record) -> SprtVerdict {
+        let llr = self.llr(record);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtVerdict::Stronger
+        } else if llr <= lower {
+            SprtVerdict::NotStronger
+        } else {
+            SprtVerdict::Undecided
+        }
+    }
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self::new(0.0, 10.0)
+    }
+}
+
+impl fmt::Display for Sprt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "elo0 {}, elo1 {}, alpha {}, beta {}", self.elo0, self.elo1, self.alpha, self.beta)
+    }
+}
+
+impl fmt::Display for SprtVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::Stronger => "stronger",
+            Self::NotStronger => "not stronger",
+            Self::Undecided => "undecided",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tournament::Record;
+
+    use super::{Sprt, SprtVerdict};
+
+    fn record(wins: u32, draws: u32, losses: u32) -> Record {
+        Record { wins, draws, losses, ..Default::default() }
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!((lower + 2.944).abs() < 0.001);
+
+        assert_eq!(sprt.llr(&record(0, 0, 0)), 0.0);
+        assert_eq!(sprt.verdict(&record(2, 0, 0)), SprtVerdict::Undecided);
+        assert_eq!(sprt.verdict(&record(300, 50, 150)), SprtVerdict::Stronger);
+        assert_eq!(sprt.verdict(&record(150, 50, 300)), SprtVerdict::NotStronger);
+        assert!(sprt.llr(&record(60, 10, 40)) > sprt.llr(&record(50, 10, 50)));
+    }
+
+    #[test]
+    fn test_one_sided_results() {
+        let sprt = Sprt::default();
+        let llr = sprt.llr(&record(30, 0, 0));
+        assert!(llr.is_finite() && llr > 0.0);
+        assert_eq!(sprt.verdict(&record(100, 0, 0)), SprtVerdict::Stronger);
+        assert_eq!(sprt.verdict(&record(0, 100, 0)), SprtVerdict::NotStronger);
+    }
+}
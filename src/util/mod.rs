@@ -14,7 +14,6 @@ mod zero;
 pub(crate) use assert::*;
 pub use convert::*;
 pub use error::*;
-pub use macros::*;
 pub use result::*;
 pub use unwrap::*;
 pub use perform::*;
@@ -32,13 +32,27 @@ pub struct ElementBuilder<'a> {
 
 impl Element {
     /// Creates a new XML element builder.
-    pub fn new(name: &str) -> ElementBuilder {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(name: &str) -> ElementBuilder<'_> {
         ElementBuilder::new(name)
     }
 
     /// Deserializes an XML node tree
     /// from the given XML event reader.
     pub fn read_from<R>(reader: &mut Reader<R>) -> Result<Element> where R: BufRead {
+        Self::read_until(reader, false)?.ok_or(Error::Eof)
+    }
+
+    /// Deserializes the next XML node tree within an enclosing element from
+    /// the given XML event reader, or `None` once the enclosing element is
+    /// closed.
+    pub fn read_child_from<R>(reader: &mut Reader<R>) -> Result<Option<Element>> where R: BufRead {
+        Self::read_until(reader, true)
+    }
+
+    /// Deserializes an XML node tree, optionally stopping at the closing tag
+    /// of the enclosing element rather than skipping it.
+    fn read_until<R>(reader: &mut Reader<R>, stop_at_parent_end: bool) -> Result<Option<Element>> where R: BufRead {
         let mut node_stack = VecDeque::<Element>::new();
         let mut buf = Vec::new();
         
@@ -56,7 +70,7 @@ impl Element {
                         parent.childs.push(node);
                         node_stack.push_back(parent);
                     } else {
-                        break Ok(node);
+                        break Ok(Some(node));
                     }
                 },
                 Ok(Event::End(ref end)) => {
@@ -66,8 +80,10 @@ impl Element {
                             parent.childs.push(node);
                             node_stack.push_back(parent);
                         } else {
-                            break Ok(node);
+                            break Ok(Some(node));
                         }
+                    } else if stop_at_parent_end {
+                        break Ok(None);
                     } else {
                         error!("Found closing element </{}> without an opening element before", str::from_utf8(end.name().as_ref())?);
                     }
@@ -103,7 +119,7 @@ impl Element {
     fn write_to_impl<W>(&self, writer: &mut Writer<W>) -> Result<()> where W: Write {
         let start = BytesStart::from(self);
         
-        if self.childs.is_empty() {
+        if self.childs.is_empty() && self.content.is_empty() {
             // Write self-closing tag, e.g. <Element/>
             writer.write_event(Event::Empty(start))?;
         } else {
@@ -143,7 +159,7 @@ impl Element {
     }
 
     /// Fetches the childs.
-    pub fn childs<'a>(&'a self) -> impl Iterator<Item=&'a Element> {
+    pub fn childs(&self) -> impl Iterator<Item=&Element> {
         self.childs.iter()
     }
     
@@ -179,7 +195,7 @@ impl<'a> ElementBuilder<'a> {
     /// Creates a new XML node builder with the
     /// specified tag name.
     pub fn new(name: &'a str) -> Self {
-        Self { name: name, content: "", attributes: HashMap::new(), childs: Vec::new() }
+        Self { name, content: "", attributes: HashMap::new(), childs: Vec::new() }
     }
     
     /// Sets the tag name of the XML node.
@@ -261,7 +277,6 @@ impl<'a> TryFrom<&BytesStart<'a>> for Element {
             name: str::from_utf8(start.name().as_ref())?.to_owned(),
             content: String::new(),
             attributes: start.attributes()
-                .into_iter()
                 .map(|res| {
                     let attribute = res?;
                     let key = str::from_utf8(attribute.key.as_ref())?.to_owned();
@@ -286,16 +301,33 @@ impl<'a> From<&'a Element> for BytesStart<'a> {
 
 #[cfg(test)]
 mod tests {
+    use quick_xml::{events::Event, Reader};
+
     use super::Element;
 
     #[test]
     fn test_write() {
         assert_eq!("<Test/>", format!("{}", Element::new("Test").build()));
-        assert_eq!("<A><B/><C/></A>", format!("{}", Element::new("A").child(Element::new("B")).child(Element::new("C")).build()))
+        assert_eq!("<A><B/><C/></A>", format!("{}", Element::new("A").child(Element::new("B")).child(Element::new("C")).build()));
+        assert_eq!("<A>text</A>", format!("{}", Element::new("A").content("text").build()));
     }
 
     #[test]
     fn test_read() {
         assert_eq!("<Test/>".parse::<Element>().unwrap(), Element::new("Test").build());
     }
+
+    #[test]
+    fn test_read_child() {
+        let mut reader = Reader::from_str("<protocol><A/><B></B></protocol><C/>");
+        assert!(matches!(reader.read_event(), Ok(Event::Start(_))));
+        assert_eq!(Element::read_child_from(&mut reader).unwrap(), Some(Element::new("A").build()));
+        assert_eq!(Element::read_child_from(&mut reader).unwrap(), Some(Element::new("B").build()));
+        assert_eq!(Element::read_child_from(&mut reader).unwrap(), None);
+
+        // Reading a single element skips the closing tag instead
+        let mut reader = Reader::from_str("<protocol></protocol><C/>");
+        assert!(matches!(reader.read_event(), Ok(Event::Start(_))));
+        assert_eq!(Element::read_from(&mut reader).unwrap(), Element::new("C").build());
+    }
 }
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crossterm::{execute, queue};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use socha_client_2024::game::{Renderer, Ship, State};
+use socha_client_2024::protocol::GameResult;
+use socha_client_2024::util::{Error, Result};
+
+/// An interactive terminal viewer that steps through the states of a game.
+pub struct Viewer {
+    states: Vec<State>,
+    result: Option<GameResult>,
+    index: usize,
+    coordinates: bool,
+    renderer: Renderer,
+}
+
+impl Viewer {
+    /// Creates a viewer for the given states, starting at the first one.
+    pub fn new(states: Vec<State>, result: Option<GameResult>) -> Self {
+        Self { states, result, index: 0, coordinates: false, renderer: Renderer::new() }
+    }
+
+    /// Runs the viewer until the user quits.
+    pub fn run(mut self) -> Result<()> {
+        if self.states.is_empty() {
+            return Err(Error::InvalidState("No states to view".to_owned()));
+        }
+
+        self.renderer = self.renderer.color(true);
+        let mut stdout = io::stdout();
+        enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+
+        let result = self.event_loop(&mut stdout);
+
+        execute!(stdout, Show, LeaveAlternateScreen)?;
+        disable_raw_mode()?;
+        result
+    }
+
+    /// Renders the current state and handles key presses until the user quits.
+    fn event_loop(&mut self, out: &mut impl Write) -> Result<()> {
+        loop {
+            queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+            for line in self.render().lines() {
+                // Raw mode doesn't return the carriage on line feeds
+                write!(out, "{}\r\n", line)?;
+            }
+            out.flush()?;
+
+            if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = read()? {
+                match code {
+                    KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => self.step(1),
+                    KeyCode::Left | KeyCode::Char('h') | KeyCode::Backspace => self.step(-1),
+                    KeyCode::PageDown => self.step(10),
+                    KeyCode::PageUp => self.step(-10),
+                    KeyCode::Home => self.index = 0,
+                    KeyCode::End => self.index = self.states.len() - 1,
+                    KeyCode::Char('c') => self.toggle_coordinates(),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    /// Moves by the given number of states, stopping at the first and last one.
+    fn step(&mut self, delta: isize) {
+        self.index = self.index.saturating_add_signed(delta).min(self.states.len() - 1);
+    }
+
+    /// Toggles whether field coordinates are shown.
+    fn toggle_coordinates(&mut self) {
+        self.coordinates = !self.coordinates;
+        self.renderer = self.renderer.coordinates(self.coordinates);
+    }
+
+    /// Renders the current state as text.
+    fn render(&self) -> String {
+        let state = &self.states[self.index];
+        let mut text = format!(
+            "Turn {} (round {}), state {}/{}, {} to move\n\n{}\n",
+            state.turn(), state.turn() / 2 + 1, self.index + 1, self.states.len(), state.current_team(), self.renderer.render(state),
+        );
+        for ship in state.ships() {
+            text += &Self::render_ship(ship);
+        }
+        text += &format!("\nLast move: {}\n", state.last_move().map(|m| m.to_string()).unwrap_or_else(|| "none".to_owned()));
+
+        if self.index == self.states.len() - 1 {
+            if let Some(reason) = state.end_reason() {
+                text += &format!("Game over: {}\n", reason);
+            }
+            if let Some(result) = &self.result {
+                let winner = result.winner().as_ref().map(|w| w.team().to_string()).unwrap_or_else(|| "none".to_owned());
+                text += &format!("Winner: {}\n", winner);
+            }
+        }
+
+        text += "\n←/h back  →/l/space forward  PgUp/PgDn ±10  Home/End first/last  c coordinates  q quit\n";
+        text
+    }
+
+    /// Renders the panel for a ship.
+    fn render_ship(ship: Ship) -> String {
+        format!(
+            "Ship {:<3}  at {}  facing {:<10}  speed {}  coal {}  passengers {}  points {:>2}  free turns {}\n",
+            ship.team, ship.position, ship.direction, ship.speed, ship.coal, ship.passengers, ship.points, ship.free_turns,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr};
+
+    use crate::load_states;
+
+    use super::Viewer;
+
+    #[test]
+    fn test_steps_through_example_game() {
+        let path = PathBuf::from_str(env!("CARGO_MANIFEST_DIR")).unwrap().join("test-resources").join("example-game");
+        let (states, result) = load_states(&path).unwrap();
+        let mut viewer = Viewer::new(states, result);
+        assert_eq!(viewer.states[0].turn(), 0);
+        assert!(viewer.render().contains("Last move: none"));
+
+        viewer.step(-1);
+        assert_eq!(viewer.index, 0);
+        viewer.step(1);
+        assert!(viewer.render().contains(&format!("Last move: {}", viewer.states[1].last_move().unwrap())));
+
+        viewer.step(1000);
+        assert_eq!(viewer.index, viewer.states.len() - 1);
+        assert!(viewer.render().contains("Game over"));
+    }
+}